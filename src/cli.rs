@@ -1,176 +1,232 @@
-use chrono::{DateTime, Utc};
-use colored::*;
-use rustyline::{
-    completion::Completer,
-    error::ReadlineError,
-    highlight::Highlighter,
-    hint::{Hinter, HistoryHinter},
-    validate::Validator,
-    Editor, Helper,
-};
-use std::{
-    borrow::Cow::{self, Owned},
-    env,
-};
-
-use crate::{
-    parse::{interpreter, lexer, parser},
-    stdlib::object::Object,
-    Context,
-};
-
-const VERSION: &str = env!("CARGO_PKG_VERSION");
-
-struct MyHelper {
-    hinter: HistoryHinter,
-}
-
-impl MyHelper {
-    fn new() -> Self {
-        Self {
-            hinter: HistoryHinter {},
-        }
-    }
-
-    fn is_contains_number(&self, line: &str) -> bool {
-        // TODO: floats, inf and NaN
-        line.chars().any(|c| c.is_ascii_digit())
-    }
-
-    fn is_contains_bools(&self, line: &str) -> bool {
-        line.contains("true") || line.contains("false")
-    }
-}
-
-impl Helper for MyHelper {}
-impl Validator for MyHelper {}
-impl Completer for MyHelper {
-    type Candidate = String;
-
-    fn complete(
-        &self,
-        line: &str,
-        pos: usize,
-        ctx: &rustyline::Context<'_>,
-    ) -> rustyline::Result<(usize, Vec<Self::Candidate>)> {
-        let _ = (line, pos, ctx);
-        if let Some(s) = self.hinter.hint(line, pos, ctx) {
-            return Ok((pos, vec![s]));
-        };
-        Ok((0, Vec::with_capacity(0)))
-    }
-
-    fn update(&self, line: &mut rustyline::line_buffer::LineBuffer, start: usize, elected: &str) {
-        let end = line.pos();
-        line.replace(start..end, elected)
-    }
-}
-
-impl Hinter for MyHelper {
-    type Hint = String;
-
-    fn hint(&self, line: &str, pos: usize, ctx: &rustyline::Context<'_>) -> Option<Self::Hint> {
-        self.hinter.hint(line, pos, ctx)
-    }
-}
-
-impl Highlighter for MyHelper {
-    fn highlight<'l>(&self, line: &'l str, _: usize) -> Cow<'l, str> {
-        let mut copy = line.to_owned();
-        if self.is_contains_number(line) {
-            copy = highlight_numbers(&copy);
-        }
-        if self.is_contains_bools(line) {
-            copy = highlight_bools(&copy);
-        }
-        Owned(copy)
-    }
-}
-
-fn highlight_numbers(s: &str) -> String {
-    s.chars()
-        .map(|c| {
-            if c.is_ascii_digit() {
-                c.to_string().yellow().to_string()
-            } else {
-                c.to_string()
-            }
-        })
-        .collect()
-}
-
-fn highlight_bools(s: &str) -> String {
-    s.to_string()
-        .replace("true", &"true".purple().to_string())
-        .replace("false", &"false".purple().to_string())
-}
-
-fn highlight_items(res: Object) -> String {
-    highlight_bools(&highlight_numbers(&format!("{}", res)))
-}
-
-pub fn run_repl() {
-    let h = MyHelper::new();
-    let mut rl = Editor::<MyHelper>::new();
-    rl.set_helper(Some(h));
-    rl.load_history("history.txt").ok();
-
-    let utc: DateTime<Utc> = Utc::now();
-
-    println!(
-        "Cringelang😳 {} [{} on {} {}]\nCtrl-C to exit",
-        VERSION,
-        utc.format("%b %d %Y, %H:%M:%S").to_string(),
-        env::consts::OS,
-        env::consts::ARCH
-    );
-
-    let mut global_sym_table = Context::new();
-
-    loop {
-        let input = rl.readline(">>> ");
-        match input {
-            Ok(s) => {
-                if s.is_empty() {
-                    continue;
-                }
-                rl.add_history_entry(s.as_str());
-                let tokens = match lexer::lex(&s) {
-                    Ok(a) => a,
-                    Err(msg) => {
-                        eprintln!("{}", msg);
-                        continue;
-                    }
-                };
-
-                #[cfg(feature = "debug")]
-                println!("{:?}", tokens);
-
-                let ast = match parser::parse(tokens) {
-                    Ok(ast) => ast,
-                    Err(msg) => {
-                        eprintln!("{}", msg);
-                        continue;
-                    }
-                };
-
-                #[cfg(feature = "debug")]
-                println!("{:?}", ast);
-
-                let res = interpreter::visit(ast, &mut global_sym_table);
-                match res {
-                    Ok(res) => println!("{}", highlight_items(res)),
-                    Err(err) => eprintln!("Runtime error: {}", err),
-                }
-            }
-            Err(ReadlineError::Interrupted) => {
-                break;
-            }
-            Err(err) => {
-                eprintln!("Error: {:?}", err);
-                break;
-            }
-        }
-    }
-    rl.save_history("history.txt").unwrap();
-}
+use chrono::{DateTime, Utc};
+use colored::*;
+use regex::{Captures, Regex};
+use rustyline::{
+    completion::Completer,
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::{Hinter, HistoryHinter},
+    validate::Validator,
+    Editor, Helper,
+};
+use std::{
+    borrow::Cow::{self, Owned},
+    env,
+    io::{self, IsTerminal},
+    sync::OnceLock,
+};
+
+use crate::{
+    diagnostic::Diagnostic,
+    error::Error,
+    parse::{interpreter, lexer, parser, span::Position},
+    stdlib::object::Object,
+    Context,
+};
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+struct MyHelper {
+    hinter: HistoryHinter,
+}
+
+impl MyHelper {
+    fn new() -> Self {
+        Self {
+            hinter: HistoryHinter {},
+        }
+    }
+
+    fn is_contains_number(&self, line: &str) -> bool {
+        number_regex().is_match(line)
+    }
+
+    fn is_contains_bools(&self, line: &str) -> bool {
+        line.contains("true") || line.contains("false")
+    }
+}
+
+impl Helper for MyHelper {}
+impl Validator for MyHelper {}
+impl Completer for MyHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Self::Candidate>)> {
+        let _ = (line, pos, ctx);
+        if let Some(s) = self.hinter.hint(line, pos, ctx) {
+            return Ok((pos, vec![s]));
+        };
+        Ok((0, Vec::with_capacity(0)))
+    }
+
+    fn update(&self, line: &mut rustyline::line_buffer::LineBuffer, start: usize, elected: &str) {
+        let end = line.pos();
+        line.replace(start..end, elected)
+    }
+}
+
+impl Hinter for MyHelper {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, ctx: &rustyline::Context<'_>) -> Option<Self::Hint> {
+        self.hinter.hint(line, pos, ctx)
+    }
+}
+
+impl Highlighter for MyHelper {
+    fn highlight<'l>(&self, line: &'l str, _: usize) -> Cow<'l, str> {
+        let mut copy = line.to_owned();
+        if self.is_contains_number(line) {
+            copy = highlight_numbers(&copy);
+        }
+        if self.is_contains_bools(line) {
+            copy = highlight_bools(&copy);
+        }
+        Owned(copy)
+    }
+}
+
+/// Matches number literals, and identifiers so that digits inside names are
+/// left alone.
+fn number_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"(?x)
+            (?P<ident>[A-Za-z_$][A-Za-z0-9_$]*)
+            | (?P<number>
+                0x[0-9a-fA-F_]+ | 0o[0-7_]+ | 0b[01_]+
+                | (?:[0-9][0-9_]*(?:\.[0-9][0-9_]*)? | \.[0-9][0-9_]*)
+                  (?:[eE][+-]?[0-9][0-9_]*)?
+            )",
+        )
+        .unwrap()
+    })
+}
+
+fn highlight_numbers(s: &str) -> String {
+    number_regex()
+        .replace_all(s, |caps: &Captures| match caps.name("ident") {
+            Some(ident) if ident.as_str() == "inf" || ident.as_str() == "NaN" => {
+                ident.as_str().yellow().to_string()
+            }
+            Some(ident) => ident.as_str().to_string(),
+            None => caps[0].yellow().to_string(),
+        })
+        .into_owned()
+}
+
+fn highlight_bools(s: &str) -> String {
+    s.to_string()
+        .replace("true", &"true".purple().to_string())
+        .replace("false", &"false".purple().to_string())
+}
+
+fn highlight_items(res: Object) -> String {
+    highlight_bools(&highlight_numbers(&format!("{}", res)))
+}
+
+/// Applies the `--color=never|always|auto` switch.
+pub fn set_color_choice(choice: &str) -> Result<(), String> {
+    match choice {
+        "always" => colored::control::set_override(true),
+        "never" => colored::control::set_override(false),
+        "auto" => {
+            if !io::stderr().is_terminal() {
+                colored::control::set_override(false)
+            }
+        }
+        _ => {
+            return Err(format!(
+                "Invalid value `{}` for `--color`, expected `never`, `always` or `auto`",
+                choice
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Prints `err` to stderr, pointing into `source` where possible.
+pub fn report(err: &Error, file_name: &str, source: &str) {
+    eprint!("{}", Diagnostic::from(err).render(file_name, source));
+}
+
+/// Runs `source` through the whole pipeline in the given context.
+pub fn eval(source: &str, context: &Context) -> Result<Object, Error> {
+    eval_at(source, Position::default(), context)
+}
+
+/// Like [`eval`], for a `source` that continues a larger text at `start`.
+pub fn eval_at(source: &str, start: Position, context: &Context) -> Result<Object, Error> {
+    let tokens = lexer::lex(source, start)?;
+
+    #[cfg(feature = "debug")]
+    println!("{:?}", tokens);
+
+    let ast = parser::parse(tokens)?;
+
+    #[cfg(feature = "debug")]
+    println!("{:?}", ast);
+
+    Ok(interpreter::visit(&ast, context)?)
+}
+
+pub fn run_repl() {
+    let h = MyHelper::new();
+    let mut rl = Editor::<MyHelper>::new();
+    rl.set_helper(Some(h));
+    rl.load_history("history.txt").ok();
+
+    let utc: DateTime<Utc> = Utc::now();
+
+    println!(
+        "Cringelang😳 {} [{} on {} {}]\nCtrl-C to exit",
+        VERSION,
+        utc.format("%b %d %Y, %H:%M:%S"),
+        env::consts::OS,
+        env::consts::ARCH
+    );
+
+    let global_sym_table = Context::new();
+    // every input so far, so errors can point at earlier lines (e.g. where a
+    // variable was declared)
+    let mut session = String::new();
+
+    loop {
+        let input = rl.readline(">>> ");
+        match input {
+            Ok(s) => {
+                if s.is_empty() {
+                    continue;
+                }
+                rl.add_history_entry(s.as_str());
+                let start = Position {
+                    offset: session.len(),
+                    line: session.matches('\n').count() + 1,
+                    column: 1,
+                };
+                session.push_str(&s);
+                if !s.ends_with('\n') {
+                    session.push('\n');
+                }
+                match eval_at(&s, start, &global_sym_table) {
+                    Ok(res) => println!("{}", highlight_items(res)),
+                    Err(err) => report(&err, "<stdin>", &session),
+                }
+            }
+            Err(ReadlineError::Interrupted) => {
+                break;
+            }
+            Err(err) => {
+                eprintln!("Error: {:?}", err);
+                break;
+            }
+        }
+    }
+    rl.save_history("history.txt").unwrap();
+}
@@ -0,0 +1,248 @@
+use colored::*;
+use std::fmt::Write;
+
+use crate::{
+    error::{Error, RuntimeError, RuntimeErrorKind},
+    parse::span::Span,
+};
+
+/// Tabs in the source are shown as this many spaces, so that the underline can
+/// be lined up with plain spaces.
+const TAB_WIDTH: usize = 4;
+
+/// A secondary location worth pointing at, e.g. where a name was declared.
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Error report in the style of rustc: a header, the offending source line with
+/// the span underlined, secondary labels and an optional help note.
+pub struct Diagnostic {
+    pub title: &'static str,
+    pub message: String,
+    pub span: Option<Span>,
+    pub labels: Vec<Label>,
+    pub help: Option<String>,
+}
+
+impl From<&Error> for Diagnostic {
+    fn from(err: &Error) -> Self {
+        let (title, message, span, help) = match err {
+            Error::Io(path, err) => (
+                "error",
+                format!("Can't read '{}': {}", path, err),
+                None,
+                None,
+            ),
+            Error::Lex(err) => (
+                "syntax error",
+                err.kind.to_string(),
+                Some(err.span),
+                err.kind.help(),
+            ),
+            Error::Parse(err) => (
+                "syntax error",
+                err.kind.to_string(),
+                Some(err.span),
+                err.kind.help(),
+            ),
+            Error::Runtime(err) => (
+                "runtime error",
+                err.kind.to_string(),
+                err.span,
+                err.kind.help(),
+            ),
+        };
+        let labels = match err {
+            Error::Runtime(RuntimeError {
+                kind: RuntimeErrorKind::AssignToImmutable { name, declared, .. },
+                ..
+            }) => vec![Label {
+                span: **declared,
+                message: format!("'{}' declared here", name),
+            }],
+            _ => vec![],
+        };
+        Self {
+            title,
+            message,
+            span,
+            labels,
+            help,
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let mut out = String::new();
+        writeln!(
+            out,
+            "{}{}",
+            self.title.red().bold(),
+            format!(": {}", self.message).bold()
+        )
+        .unwrap();
+
+        let span = match self.span {
+            Some(span) => span,
+            None => return out,
+        };
+
+        let last_line = self
+            .labels
+            .iter()
+            .map(|label| label.span.start.line)
+            .chain(Some(span.start.line))
+            .max()
+            .unwrap_or(1);
+        let width = last_line.to_string().len();
+        let gutter = " ".repeat(width);
+        let pipe = "|".blue().bold();
+
+        writeln!(
+            out,
+            "{}{} {}:{}:{}",
+            gutter,
+            "-->".blue().bold(),
+            file_name,
+            span.start.line,
+            span.start.column
+        )
+        .unwrap();
+        writeln!(out, "{} {}", gutter, pipe).unwrap();
+
+        let mut snippets = vec![(span, None)];
+        snippets.extend(
+            self.labels
+                .iter()
+                .map(|label| (label.span, Some(label.message.as_str()))),
+        );
+        snippets.sort_by_key(|(span, _)| span.start.offset);
+
+        let mut previous_line = None;
+        for (span, message) in snippets {
+            let line = source.lines().nth(span.start.line - 1).unwrap_or("");
+            let line_len = line.chars().count();
+            let start = span.start.column.min(line_len + 1);
+            let end = if span.end.line == span.start.line {
+                span.end.column.max(start + 1)
+            } else {
+                line_len + 1
+            };
+            let (start, end) = (display_width(line, start - 1), display_width(line, end - 1));
+            let marker = if message.is_some() { "-" } else { "^" };
+            let underline = marker.repeat((end - start).max(1));
+            let underline = match message {
+                Some(message) => format!("{} {}", underline, message).blue().bold(),
+                None => underline.red().bold(),
+            };
+
+            // several snippets on one line share it
+            if previous_line != Some(span.start.line) {
+                writeln!(
+                    out,
+                    "{} {} {}",
+                    format!("{:>width$}", span.start.line, width = width)
+                        .blue()
+                        .bold(),
+                    pipe,
+                    line.replace('\t', &" ".repeat(TAB_WIDTH))
+                )
+                .unwrap();
+            }
+            previous_line = Some(span.start.line);
+            writeln!(
+                out,
+                "{} {} {}{}",
+                gutter,
+                pipe,
+                " ".repeat(start),
+                underline
+            )
+            .unwrap();
+        }
+
+        if let Some(help) = &self.help {
+            writeln!(out, "{} {}", gutter, pipe).unwrap();
+            writeln!(
+                out,
+                "{} {} {}: {}",
+                gutter,
+                "=".blue().bold(),
+                "help".bold(),
+                help
+            )
+            .unwrap();
+        }
+        out
+    }
+}
+
+/// Width on screen of the first `columns` characters of `line`, columns past
+/// its end count as one each.
+fn display_width(line: &str, columns: usize) -> usize {
+    line.chars()
+        .chain(std::iter::repeat(' '))
+        .take(columns)
+        .map(|ch| if ch == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cli::eval, parse::Context};
+
+    fn render(source: &str) -> String {
+        colored::control::set_override(false);
+        let err = eval(source, &Context::new()).unwrap_err();
+        Diagnostic::from(&err).render("test.cr", source)
+    }
+
+    #[test]
+    fn underlines_span_with_help() {
+        assert_eq!(
+            render("let s = \"a\\q\""),
+            "syntax error: Unknown escape sequence character: 'q'
+ --> test.cr:1:9
+  |
+1 | let s = \"a\\q\"
+  |         ^^^
+  |
+  = help: supported escapes are `\\n`, `\\r`, `\\t`, `\\0`, `\\\"`, `\\'` and `\\\\`
+"
+        );
+    }
+
+    #[test]
+    fn labels_secondary_span() {
+        assert_eq!(
+            render("let x = 1\nx = 2"),
+            "runtime error: Can't assign twice to immutable variable 'x'
+ --> test.cr:2:1
+  |
+1 | let x = 1
+  | --------- 'x' declared here
+2 | x = 2
+  | ^^^^^
+  |
+  = help: only variables declared with `let mut` can be reassigned
+"
+        );
+    }
+
+    #[test]
+    fn expands_tabs() {
+        assert_eq!(
+            render("if true {\n\t1 -\t[1]\n}"),
+            "runtime error: Operator '-' is not supported for types Int and List
+ --> test.cr:2:2
+  |
+2 |     1 -    [1]
+  |     ^^^^^^^^^^
+"
+        );
+    }
+}
@@ -0,0 +1,434 @@
+use std::{
+    error,
+    fmt::{self, Display},
+    io,
+};
+
+use crate::parse::span::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexErrorKind {
+    UnexpectedChar(char),
+    UnterminatedString,
+    UnterminatedChar,
+    EmptyChar,
+    CharTooLong,
+    UnknownEscape(char),
+    UnexpectedEol,
+    InvalidNumber(String),
+    UnterminatedBlockComment,
+}
+
+impl Display for LexErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexErrorKind::UnexpectedChar(c) => write!(f, "Unexpected character: `{}`", c),
+            LexErrorKind::UnterminatedString => write!(f, "Expected closing `\"`"),
+            LexErrorKind::UnterminatedChar => write!(f, "Expected closing `'`"),
+            LexErrorKind::EmptyChar => write!(f, "Empty char literal"),
+            LexErrorKind::CharTooLong => write!(f, "Literal must be one character long"),
+            LexErrorKind::UnknownEscape(c) => {
+                write!(f, "Unknown escape sequence character: {:?}", c)
+            }
+            LexErrorKind::UnexpectedEol => write!(f, "Unexpected EOL"),
+            LexErrorKind::InvalidNumber(msg) => write!(f, "Invalid number literal: {}", msg),
+            LexErrorKind::UnterminatedBlockComment => write!(f, "Unterminated block comment"),
+        }
+    }
+}
+
+impl LexErrorKind {
+    pub fn help(&self) -> Option<String> {
+        match self {
+            LexErrorKind::UnknownEscape(_) => Some(
+                "supported escapes are `\\n`, `\\r`, `\\t`, `\\0`, `\\\"`, `\\'` and `\\\\`"
+                    .to_string(),
+            ),
+            LexErrorKind::UnterminatedBlockComment => {
+                Some("block comments nest, every `/*` needs its own `*/`".to_string())
+            }
+            LexErrorKind::CharTooLong => {
+                Some("use double quotes for strings: `\"...\"`".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub span: Span,
+}
+
+impl LexError {
+    pub fn new(kind: LexErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.kind, self.span)
+    }
+}
+
+impl error::Error for LexError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    Expected {
+        expected: String,
+        found: String,
+    },
+    UnexpectedToken(String),
+    ExpectedExpression,
+    KeywordAsIdentifier(String),
+    /// `const` anywhere but the top level.
+    NestedConst,
+    InvalidAssignmentTarget,
+    InvalidPattern,
+    DuplicateParam(String),
+    UnexpectedEof,
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::Expected { expected, found } => {
+                write!(f, "Expected {}, found {}", expected, found)
+            }
+            ParseErrorKind::UnexpectedToken(found) => write!(f, "Unexpected token: {}", found),
+            ParseErrorKind::ExpectedExpression => write!(f, "Expected expression"),
+            ParseErrorKind::KeywordAsIdentifier(keyword) => {
+                write!(f, "Expected identifier, found keyword `{}`", keyword)
+            }
+            ParseErrorKind::NestedConst => write!(f, "`const` is only allowed at the top level"),
+            ParseErrorKind::InvalidAssignmentTarget => write!(f, "Invalid assignment target"),
+            ParseErrorKind::InvalidPattern => write!(f, "Invalid pattern"),
+            ParseErrorKind::DuplicateParam(name) => {
+                write!(f, "Parameter '{}' is declared more than once", name)
+            }
+            ParseErrorKind::UnexpectedEof => write!(f, "Unexpected EOF"),
+        }
+    }
+}
+
+impl ParseErrorKind {
+    pub fn help(&self) -> Option<String> {
+        match self {
+            ParseErrorKind::KeywordAsIdentifier(keyword) => Some(format!(
+                "`{}` is a reserved keyword, pick another name",
+                keyword
+            )),
+            ParseErrorKind::NestedConst => Some("use `let` for local variables".to_string()),
+            ParseErrorKind::InvalidAssignmentTarget => {
+                Some("only variables and indexed items can be assigned to".to_string())
+            }
+            ParseErrorKind::InvalidPattern => Some(
+                "patterns are literals, ranges of literals, names, `_` and tuples, lists \
+                 and maps of patterns, with at most one `..` in a list"
+                    .to_string(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.kind, self.span)
+    }
+}
+
+impl error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeErrorKind {
+    /// Operator applied to operands of types it doesn't support.
+    TypeMismatch {
+        op: String,
+        operands: Vec<String>,
+    },
+    UndefinedName(String),
+    /// Assignment to a `let` or `const` binding, `declared` is where it was
+    /// declared.
+    AssignToImmutable {
+        name: String,
+        constant: bool,
+        declared: Box<Span>,
+    },
+    /// `const` initialized with something that isn't a constant expression.
+    NotConstant,
+    DivisionByZero,
+    IntegerOverflow(String),
+    IndexOutOfRange {
+        index: i64,
+        len: usize,
+    },
+    NotIndexable(String),
+    ItemAssignment(String),
+    /// Map key or set item of a type that can't be hashed.
+    Unhashable(String),
+    /// Missing map key, holds its representation.
+    KeyNotFound(String),
+    NotIterable(String),
+    NotCallable(String),
+    NoMethod {
+        r#type: String,
+        name: String,
+    },
+    WrongArity {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    InvalidValue(String),
+    /// `Null` used as a condition, it's neither true nor false.
+    NullCondition,
+    /// `break` or `continue` used outside of a loop.
+    OutsideLoop(String),
+    ReturnOutsideFunction,
+    /// More nested calls than the limit allows.
+    RecursionLimit(usize),
+    /// No arm of a `match` matched, holds the representation of the value.
+    NoMatch(String),
+    /// A part of a pattern didn't match, `found` is the representation of the
+    /// value it was matched against.
+    PatternMismatch {
+        expected: String,
+        found: String,
+    },
+}
+
+impl Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeErrorKind::TypeMismatch { op, operands } => match operands.as_slice() {
+                [operand] => write!(f, "Operator '{}' is not supported for type {}", op, operand),
+                operands => write!(
+                    f,
+                    "Operator '{}' is not supported for types {}",
+                    op,
+                    operands.join(" and ")
+                ),
+            },
+            RuntimeErrorKind::UndefinedName(name) => write!(f, "Name '{}' is not defined", name),
+            RuntimeErrorKind::AssignToImmutable { name, constant, .. } => {
+                if *constant {
+                    write!(f, "Can't assign to constant '{}'", name)
+                } else {
+                    write!(f, "Can't assign twice to immutable variable '{}'", name)
+                }
+            }
+            RuntimeErrorKind::NotConstant => {
+                write!(
+                    f,
+                    "Constants must be initialized with a constant expression"
+                )
+            }
+            RuntimeErrorKind::DivisionByZero => write!(f, "Division by zero"),
+            RuntimeErrorKind::IntegerOverflow(op) => {
+                write!(f, "Integer overflow in operator '{}'", op)
+            }
+            RuntimeErrorKind::IndexOutOfRange { index, len } => {
+                write!(f, "Index {} is out of range for length {}", index, len)
+            }
+            RuntimeErrorKind::NotIndexable(r#type) => {
+                write!(f, "Object of type {} can not be indexed", r#type)
+            }
+            RuntimeErrorKind::ItemAssignment(r#type) => {
+                write!(
+                    f,
+                    "Object of type {} does not support item assignment",
+                    r#type
+                )
+            }
+            RuntimeErrorKind::Unhashable(r#type) => {
+                write!(
+                    f,
+                    "Object of type {} can not be a map key or a set item",
+                    r#type
+                )
+            }
+            RuntimeErrorKind::KeyNotFound(key) => write!(f, "Key {} is not in the map", key),
+            RuntimeErrorKind::NotIterable(r#type) => {
+                write!(f, "Object of type {} is not iterable", r#type)
+            }
+            RuntimeErrorKind::NotCallable(r#type) => {
+                write!(f, "Object of type {} is not callable", r#type)
+            }
+            RuntimeErrorKind::NoMethod { r#type, name } => {
+                write!(f, "Object of type {} has no method '{}'", r#type, name)
+            }
+            RuntimeErrorKind::WrongArity {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "'{}' takes {} argument{} but {} {} given",
+                name,
+                expected,
+                if *expected == 1 { "" } else { "s" },
+                found,
+                if *found == 1 { "was" } else { "were" }
+            ),
+            RuntimeErrorKind::InvalidValue(msg) => write!(f, "{}", msg),
+            RuntimeErrorKind::NullCondition => write!(f, "Null is neither true nor false"),
+            RuntimeErrorKind::OutsideLoop(keyword) => {
+                write!(f, "'{}' outside of a loop", keyword)
+            }
+            RuntimeErrorKind::ReturnOutsideFunction => write!(f, "'return' outside of a function"),
+            RuntimeErrorKind::RecursionLimit(limit) => {
+                write!(f, "Maximum recursion depth of {} calls exceeded", limit)
+            }
+            RuntimeErrorKind::NoMatch(value) => write!(f, "No match arm matches {}", value),
+            RuntimeErrorKind::PatternMismatch { expected, found } => write!(
+                f,
+                "Pattern didn't match: expected {}, found {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl RuntimeErrorKind {
+    pub fn help(&self) -> Option<String> {
+        match self {
+            RuntimeErrorKind::UndefinedName(name) => {
+                Some(format!("declare it first with `let {} = ...`", name))
+            }
+            RuntimeErrorKind::AssignToImmutable {
+                constant: false, ..
+            } => Some("only variables declared with `let mut` can be reassigned".to_string()),
+            RuntimeErrorKind::Unhashable(_) => Some(
+                "only numbers, strings, chars, bools, null and tuples of them are hashable"
+                    .to_string(),
+            ),
+            RuntimeErrorKind::RecursionLimit(_) => {
+                Some("make sure every recursive function has a case that stops".to_string())
+            }
+            RuntimeErrorKind::NullCondition => {
+                Some("use `??` to give a default or compare with `== null`".to_string())
+            }
+            RuntimeErrorKind::NoMatch(_) => {
+                Some("add a `_ => ...` arm to handle the remaining values".to_string())
+            }
+            RuntimeErrorKind::NotConstant => {
+                Some("only literals, operators and other constants can be used".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Error raised while evaluating the AST. Errors coming from `Object` operators
+/// don't know where they happened, the interpreter fills the span in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    pub span: Option<Span>,
+}
+
+impl RuntimeError {
+    pub fn new(kind: RuntimeErrorKind) -> Self {
+        Self { kind, span: None }
+    }
+
+    pub fn type_mismatch(op: &str, operands: &[String]) -> Self {
+        Self::new(RuntimeErrorKind::TypeMismatch {
+            op: op.to_string(),
+            operands: operands.to_vec(),
+        })
+    }
+
+    /// Sets the span unless a more precise one is already known.
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{} at {}", self.kind, span),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl error::Error for RuntimeError {}
+
+#[derive(Debug)]
+pub enum Error {
+    /// Reading the file at the path failed.
+    Io(String, io::Error),
+    Lex(LexError),
+    Parse(ParseError),
+    Runtime(RuntimeError),
+}
+
+impl Error {
+    /// Exit code following the `sysexits.h` convention.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Io(..) => 66,
+            Error::Lex(_) | Error::Parse(_) => 65,
+            Error::Runtime(_) => 70,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(path, err) => write!(f, "Error: Can't read '{}': {}", path, err),
+            Error::Lex(err) => write!(f, "Syntax error: {}", err),
+            Error::Parse(err) => write!(f, "Syntax error: {}", err),
+            Error::Runtime(err) => write!(f, "Runtime error: {}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(_, err) => Some(err),
+            Error::Lex(err) => Some(err),
+            Error::Parse(err) => Some(err),
+            Error::Runtime(err) => Some(err),
+        }
+    }
+}
+
+impl From<LexError> for Error {
+    fn from(err: LexError) -> Self {
+        Error::Lex(err)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Parse(err)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(err: RuntimeError) -> Self {
+        Error::Runtime(err)
+    }
+}
@@ -1,23 +1,57 @@
 mod cli;
+mod diagnostic;
+mod error;
 mod parse;
 mod stdlib;
 
-use cli::run_repl;
-use parse::{interpreter, lexer, parser, Context};
-use std::{env, fs};
+use cli::{eval, report, run_repl, set_color_choice};
+use error::Error;
+use parse::Context;
+use std::{env, fs, process, thread};
+
+/// Stack of the thread running the interpreter, which recurses for every call
+/// in the script. Enough for `DEFAULT_RECURSION_LIMIT` calls in debug builds.
+const STACK_SIZE: usize = 64 * 1024 * 1024;
 
 fn main() {
-    match env::args().nth(1) {
-        Some(filename) => run_file(&filename),
-        None => run_repl(),
+    let mut filename = None;
+    let mut color = "auto".to_string();
+    for arg in env::args().skip(1) {
+        match arg.strip_prefix("--color=") {
+            Some(choice) => color = choice.to_string(),
+            None => filename = Some(arg),
+        }
+    }
+    if let Err(msg) = set_color_choice(&color) {
+        eprintln!("{}", msg);
+        process::exit(64);
+    }
+
+    let interpreter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || match filename {
+            Some(filename) => run_file(&filename),
+            None => run_repl(),
+        })
+        .expect("failed to spawn the interpreter thread");
+    if interpreter.join().is_err() {
+        process::exit(101);
     }
 }
 
 fn run_file(filename: &str) {
-    let source = fs::read_to_string(filename).unwrap();
-    let tokens = lexer::lex(&source).expect("Failed to lex");
-    let ast = parser::parse(tokens).expect("Failed to parse");
+    let source = match fs::read_to_string(filename) {
+        Ok(source) => source,
+        Err(err) => fail(&Error::Io(filename.to_string(), err), filename, ""),
+    };
+
+    let global_sym_table = Context::new();
+    if let Err(err) = eval(&source, &global_sym_table) {
+        fail(&err, filename, &source);
+    }
+}
 
-    let mut global_sym_table = Context::new();
-    interpreter::visit(ast, &mut global_sym_table).expect("Fatal error");
+fn fail(err: &Error, filename: &str, source: &str) -> ! {
+    report(err, filename, source);
+    process::exit(err.exit_code());
 }
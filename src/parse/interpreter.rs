@@ -1,86 +1,1518 @@
-use std::{
-    cell::RefCell,
-    collections::HashMap,
-    ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Not, Rem, Shl, Shr, Sub},
-    rc::Rc,
-};
-
-use super::{parser::AstNode, tokens::Operator::*};
-use crate::stdlib::{object::Object, ops::Pow};
-
-#[derive(Default, Debug)]
-pub struct Context {
-    symbols: HashMap<String, Object>,
-    parent: Option<Box<Context>>,
-}
-
-impl Context {
-    pub fn new() -> Self {
-        Self {
-            ..Default::default()
-        }
-    }
-
-    fn get(&self, var_name: &str) -> Option<Object> {
-        Some(match self.symbols.get(var_name) {
-            Some(value) => value.clone(),
-            None => self.parent.as_ref()?.get(var_name)?,
-        })
-    }
-
-    fn set(&mut self, var_name: String, value: &Object) {
-        self.symbols.insert(var_name, value.clone());
-    }
-}
-
-pub fn visit(node: AstNode, context: &mut Context) -> Result<Object, String> {
-    Ok(match node {
-        AstNode::Number(n) => Object::Number(n),
-        AstNode::Bool(b) => Object::Bool(b),
-        AstNode::Char(c) => Object::Char(c),
-        AstNode::String(s) => Object::String(Rc::new(RefCell::new(s))),
-        AstNode::UnOp(op, node) => match op {
-            Plus => Object::Number(visit(*node, context)?.num()?),
-            Minus => Object::Number(visit(*node, context)?.num()?).mul(&Object::Number(-1.0))?,
-            Exclamation => visit(*node, context)?.not()?,
-            _ => todo!(),
-        },
-        AstNode::BinOp(left_node, op, right_node) => match op {
-            Plus => visit(*left_node, context)?.add(&visit(*right_node, context)?)?,
-            Minus => visit(*left_node, context)?.sub(&visit(*right_node, context)?)?,
-            Star => visit(*left_node, context)?.mul(&visit(*right_node, context)?)?,
-            Slash => visit(*left_node, context)?.div(&visit(*right_node, context)?)?,
-            StarStar => visit(*left_node, context)?.pow(&visit(*right_node, context)?)?,
-            Percent => visit(*left_node, context)?.rem(&visit(*right_node, context)?)?,
-            Pipe => visit(*left_node, context)?.bitor(&visit(*right_node, context)?)?,
-            Ampersand => visit(*left_node, context)?.bitand(&visit(*right_node, context)?)?,
-            Caret => visit(*left_node, context)?.bitxor(&visit(*right_node, context)?)?,
-            GreaterGreater => visit(*left_node, context)?.shr(&visit(*right_node, context)?)?,
-            LessLess => visit(*left_node, context)?.shl(&visit(*right_node, context)?)?,
-            EqualsEquals => visit(*left_node, context)?.eq(&visit(*right_node, context)?),
-            ExclamationEquals => visit(*left_node, context)?.ne(&visit(*right_node, context)?),
-            Greater => visit(*left_node, context)?.gt(&visit(*right_node, context)?),
-            Less => visit(*left_node, context)?.lt(&visit(*right_node, context)?),
-            GreaterEquals => visit(*left_node, context)?.gte(&visit(*right_node, context)?),
-            LessEquals => visit(*left_node, context)?.lte(&visit(*right_node, context)?),
-            _ => todo!(),
-        },
-        AstNode::VarCreate(var_name, node) => {
-            let val = visit(*node, context)?;
-            context.set(var_name, &val);
-            val
-        }
-        AstNode::VarAssign(var_name, node) => {
-            if context.symbols.contains_key(&var_name) {
-                let val = visit(*node, context)?;
-                context.set(var_name, &val);
-                val
-            } else {
-                return Err(format!("Name '{}' is not defined", var_name));
-            }
-        }
-        AstNode::VarAccess(var_name) => context
-            .get(&var_name)
-            .ok_or_else(|| format!("Name '{}' is not defined", var_name))?,
-    })
-}
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Ordering,
+    collections::HashMap,
+    ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Neg, Not, Rem, Shl, Shr, Sub},
+    rc::Rc,
+};
+
+use super::{
+    parser::{AstKind, AstNode, Declaration, MatchArm, Pattern, PatternKind},
+    span::Span,
+    tokens::{Operator, Operator::*},
+};
+use crate::{
+    error::{RuntimeError, RuntimeErrorKind},
+    stdlib::{
+        function::Function,
+        map::Map,
+        object::Object,
+        ops::{Pow, TypeOf},
+        set::Set,
+    },
+};
+
+#[derive(Debug)]
+struct Binding {
+    value: Object,
+    declaration: Declaration,
+    /// Where the binding was declared.
+    span: Span,
+}
+
+/// Calls a program may nest before `RecursionLimit` is raised, by default.
+pub const DEFAULT_RECURSION_LIMIT: usize = 1000;
+
+/// Number of function calls in progress, shared by all scopes of a program.
+#[derive(Debug)]
+struct CallDepth {
+    depth: Cell<usize>,
+    limit: usize,
+}
+
+impl Default for CallDepth {
+    fn default() -> Self {
+        Self {
+            depth: Cell::new(0),
+            limit: DEFAULT_RECURSION_LIMIT,
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+struct Scope {
+    symbols: RefCell<HashMap<String, Binding>>,
+    parent: Option<Context>,
+    calls: Rc<CallDepth>,
+}
+
+/// Handle to a scope of variables. Clones share the scope, so a function can
+/// keep the scope it was defined in alive and see later changes to it.
+#[derive(Default, Debug, Clone)]
+pub struct Context(Rc<Scope>);
+
+impl Context {
+    pub fn new() -> Self {
+        Self {
+            ..Default::default()
+        }
+    }
+
+    /// Global scope of a program that may nest at most `limit` calls.
+    pub fn with_recursion_limit(limit: usize) -> Self {
+        Self(Rc::new(Scope {
+            calls: Rc::new(CallDepth {
+                depth: Cell::new(0),
+                limit,
+            }),
+            ..Default::default()
+        }))
+    }
+
+    /// Fresh scope nested in `parent`.
+    pub fn with_parent(parent: &Context) -> Self {
+        Self(Rc::new(Scope {
+            symbols: Default::default(),
+            parent: Some(parent.clone()),
+            calls: parent.0.calls.clone(),
+        }))
+    }
+
+    fn get(&self, var_name: &str) -> Option<Object> {
+        self.declaration(var_name).map(|(value, _)| value)
+    }
+
+    fn declaration(&self, var_name: &str) -> Option<(Object, Declaration)> {
+        Some(match self.0.symbols.borrow().get(var_name) {
+            Some(binding) => (binding.value.clone(), binding.declaration),
+            None => self.0.parent.as_ref()?.declaration(var_name)?,
+        })
+    }
+
+    fn set(&self, var_name: String, value: &Object, declaration: Declaration, span: Span) {
+        let binding = Binding {
+            value: value.clone(),
+            declaration,
+            span,
+        };
+        self.0.symbols.borrow_mut().insert(var_name, binding);
+    }
+
+    /// Overwrites an existing mutable variable in the nearest scope that
+    /// defines it.
+    fn assign(&self, var_name: &str, value: &Object) -> Result<(), RuntimeErrorKind> {
+        if let Some(binding) = self.0.symbols.borrow_mut().get_mut(var_name) {
+            if binding.declaration != Declaration::LetMut {
+                return Err(RuntimeErrorKind::AssignToImmutable {
+                    name: var_name.to_string(),
+                    constant: binding.declaration == Declaration::Const,
+                    declared: Box::new(binding.span),
+                });
+            }
+            binding.value = value.clone();
+            return Ok(());
+        }
+        match &self.0.parent {
+            Some(parent) => parent.assign(var_name, value),
+            None => Err(RuntimeErrorKind::UndefinedName(var_name.to_string())),
+        }
+    }
+}
+
+/// Reasons for evaluation to stop before a node produced its value.
+enum Unwind {
+    Error(RuntimeError),
+    Break(Object, Span),
+    Continue(Span),
+    Return(Object, Span),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(err: RuntimeError) -> Self {
+        Unwind::Error(err)
+    }
+}
+
+impl Unwind {
+    /// Turns control flow that escaped its loop or function into an error.
+    fn into_error(self) -> RuntimeError {
+        let (kind, span) = match self {
+            Unwind::Error(err) => return err,
+            Unwind::Break(_, span) => (RuntimeErrorKind::OutsideLoop("break".to_string()), span),
+            Unwind::Continue(span) => (RuntimeErrorKind::OutsideLoop("continue".to_string()), span),
+            Unwind::Return(_, span) => (RuntimeErrorKind::ReturnOutsideFunction, span),
+        };
+        RuntimeError::new(kind).with_span(span)
+    }
+}
+
+pub fn visit(node: &AstNode, context: &Context) -> Result<Object, RuntimeError> {
+    eval(node, context).map_err(Unwind::into_error)
+}
+
+/// Calls `callee` with already evaluated arguments.
+pub fn call(callee: &Object, args: Vec<Object>) -> Result<Object, RuntimeError> {
+    let function = match callee {
+        Object::Function(function) => function,
+        _ => {
+            return Err(RuntimeError::new(RuntimeErrorKind::NotCallable(
+                callee.r#typeof(),
+            )))
+        }
+    };
+    if args.len() != function.params.len() {
+        return Err(RuntimeError::new(RuntimeErrorKind::WrongArity {
+            name: function.to_string(),
+            expected: function.params.len(),
+            found: args.len(),
+        }));
+    }
+    let calls = &function.env.0.calls;
+    if calls.depth.get() >= calls.limit {
+        return Err(RuntimeError::new(RuntimeErrorKind::RecursionLimit(
+            calls.limit,
+        )));
+    }
+    let scope = Context::with_parent(&function.env);
+    for ((param, span), arg) in function.params.iter().zip(args) {
+        scope.set(param.clone(), &arg, Declaration::Let, *span);
+    }
+    calls.depth.set(calls.depth.get() + 1);
+    let result = eval(&function.body, &scope);
+    calls.depth.set(calls.depth.get() - 1);
+    match result {
+        Ok(value) | Err(Unwind::Return(value, _)) => Ok(value),
+        Err(unwind) => Err(unwind.into_error()),
+    }
+}
+
+/// Evaluates `node`. Every kind of node that needs more than a line is handled
+/// by its own function: this one recurses for every level of the AST, so its
+/// stack frame is kept small.
+fn eval(node: &AstNode, context: &Context) -> Result<Object, Unwind> {
+    let span = node.span;
+    match &node.kind {
+        AstKind::Number(n) => Ok(Object::Number(*n)),
+        AstKind::Int(n) => Ok(Object::Int(*n)),
+        AstKind::BigInt(n) => Ok(Object::BigInt(Rc::new(n.clone()))),
+        AstKind::Bool(b) => Ok(Object::Bool(*b)),
+        AstKind::Char(c) => Ok(Object::Char(*c)),
+        AstKind::String(s) => Ok(Object::String(Rc::new(RefCell::new(s.clone())))),
+        AstKind::Null => Ok(Object::Null),
+        AstKind::UnOp(op, operand) => eval_unop(*op, operand, span, context),
+        AstKind::BinOp(left, op, right) => eval_binop(left, *op, right, span, context),
+        AstKind::VarCreate(name, declaration, value) => {
+            eval_var_create(name, *declaration, value, span, context)
+        }
+        AstKind::LetPattern(pattern, declaration, value) => {
+            eval_let_pattern(pattern, *declaration, value, context)
+        }
+        AstKind::DestructuringAssign(target, value) => {
+            eval_destructuring_assign(target, value, context)
+        }
+        AstKind::VarAssign(name, op, value) => eval_var_assign(name, *op, value, span, context),
+        AstKind::IndexAssign(container, index, op, value) => {
+            eval_index_assign(container, index, *op, value, span, context)
+        }
+        AstKind::VarAccess(name) => context
+            .get(name)
+            .ok_or_else(|| error_at(RuntimeErrorKind::UndefinedName(name.clone()), span)),
+        AstKind::Program(nodes) => eval_statements(nodes, context),
+        AstKind::Block(nodes) => eval_statements(nodes, &Context::with_parent(context)),
+        AstKind::If(condition, then, otherwise) => {
+            eval_if(condition, then, otherwise.as_deref(), context)
+        }
+        AstKind::While(condition, body) => eval_while(condition, body, context),
+        AstKind::For(var, iterable, body) => eval_for(var, iterable, body, span, context),
+        AstKind::In(item, container) => eval_in(item, container, span, context),
+        AstKind::ShortCircuit(left, op, right) => eval_short_circuit(left, *op, right, context),
+        AstKind::FnDecl(name, params, body) => Ok(eval_fn_decl(name, params, body, span, context)),
+        AstKind::Return(value) => Err(Unwind::Return(eval_optional(value, context)?, span)),
+        AstKind::Call(callee, args) => eval_call(callee, args, span, context),
+        AstKind::MethodCall(receiver, name, args, optional) => {
+            eval_method_call(receiver, name, args, *optional, span, context)
+        }
+        AstKind::List(items) => Ok(Object::list(eval_items(items, context)?)),
+        AstKind::Tuple(items) => Ok(Object::tuple(eval_items(items, context)?)),
+        AstKind::Set(items) => eval_set(items, context),
+        AstKind::Map(entries) => eval_map(entries, context),
+        AstKind::Index(container, index) => eval_index(container, index, span, context),
+        AstKind::Match(value, arms) => eval_match(value, arms, context),
+        AstKind::Break(value) => Err(Unwind::Break(eval_optional(value, context)?, span)),
+        AstKind::Continue => Err(Unwind::Continue(span)),
+    }
+}
+
+fn error_at(kind: RuntimeErrorKind, span: Span) -> Unwind {
+    Unwind::Error(RuntimeError::new(kind).with_span(span))
+}
+
+fn eval_unop(
+    op: Operator,
+    operand: &AstNode,
+    span: Span,
+    context: &Context,
+) -> Result<Object, Unwind> {
+    let value = eval(operand, context)?;
+    Ok(match op {
+        Plus => match value {
+            Object::Int(_) | Object::BigInt(_) => Ok(value),
+            Object::Null => Err(RuntimeError::type_mismatch("+", &[value.r#typeof()])),
+            _ => value.num().map(Object::Number),
+        },
+        Minus => value.neg(),
+        Exclamation => value.not(),
+        _ => todo!(),
+    }
+    .map_err(|err| err.with_span(span))?)
+}
+
+fn eval_binop(
+    left: &AstNode,
+    op: Operator,
+    right: &AstNode,
+    span: Span,
+    context: &Context,
+) -> Result<Object, Unwind> {
+    let left = eval(left, context)?;
+    let right = eval(right, context)?;
+    Ok(binary_op(&left, op, &right).map_err(|err| err.with_span(span))?)
+}
+
+fn eval_var_create(
+    name: &str,
+    declaration: Declaration,
+    value: &AstNode,
+    span: Span,
+    context: &Context,
+) -> Result<Object, Unwind> {
+    if declaration == Declaration::Const {
+        check_constant(value, context)?;
+    }
+    let value = eval(value, context)?;
+    context.set(name.to_string(), &value, declaration, span);
+    Ok(value)
+}
+
+fn eval_let_pattern(
+    pattern: &Pattern,
+    declaration: Declaration,
+    value: &AstNode,
+    context: &Context,
+) -> Result<Object, Unwind> {
+    if declaration == Declaration::Const {
+        check_constant(value, context)?;
+    }
+    let value = eval(value, context)?;
+    let mut bindings = vec![];
+    match_pattern(pattern, &value, context, &mut bindings)?;
+    for (name, item, span) in bindings {
+        context.set(name, &item, declaration, span);
+    }
+    Ok(value)
+}
+
+fn eval_destructuring_assign(
+    target: &AstNode,
+    value: &AstNode,
+    context: &Context,
+) -> Result<Object, Unwind> {
+    let value = eval(value, context)?;
+    assign_destructured(target, value.clone(), context)?;
+    Ok(value)
+}
+
+fn eval_var_assign(
+    name: &str,
+    op: Option<Operator>,
+    value: &AstNode,
+    span: Span,
+    context: &Context,
+) -> Result<Object, Unwind> {
+    let current = match context.get(name) {
+        Some(current) => current,
+        None => {
+            return Err(error_at(
+                RuntimeErrorKind::UndefinedName(name.to_string()),
+                span,
+            ))
+        }
+    };
+    let mut value = eval(value, context)?;
+    if let Some(op) = op {
+        value = binary_op(&current, op, &value).map_err(|err| err.with_span(span))?;
+    }
+    // goes to the nearest scope defining the name, never creates one
+    context
+        .assign(name, &value)
+        .map_err(|kind| error_at(kind, span))?;
+    Ok(value)
+}
+
+fn eval_index_assign(
+    container: &AstNode,
+    index: &AstNode,
+    op: Option<Operator>,
+    value: &AstNode,
+    span: Span,
+    context: &Context,
+) -> Result<Object, Unwind> {
+    let container = eval(container, context)?;
+    let index = eval(index, context)?;
+    let value = match op {
+        // the current value is looked up before evaluating the right side,
+        // like a variable's
+        Some(op) => {
+            let current = container.idx(&index).map_err(|err| err.with_span(span))?;
+            let value = eval(value, context)?;
+            binary_op(&current, op, &value).map_err(|err| err.with_span(span))?
+        }
+        None => eval(value, context)?,
+    };
+    container
+        .set_idx(&index, value.clone())
+        .map_err(|err| err.with_span(span))?;
+    Ok(value)
+}
+
+/// Truthiness of `node`, a `Null` is an error pointing at it.
+fn eval_condition(node: &AstNode, context: &Context) -> Result<bool, Unwind> {
+    Ok(eval(node, context)?
+        .bool()
+        .map_err(|err| err.with_span(node.span))?)
+}
+
+fn eval_if(
+    condition: &AstNode,
+    then: &AstNode,
+    otherwise: Option<&AstNode>,
+    context: &Context,
+) -> Result<Object, Unwind> {
+    if eval_condition(condition, context)? {
+        eval(then, context)
+    } else if let Some(otherwise) = otherwise {
+        eval(otherwise, context)
+    } else {
+        Ok(Object::Null)
+    }
+}
+
+fn eval_while(condition: &AstNode, body: &AstNode, context: &Context) -> Result<Object, Unwind> {
+    loop {
+        if !eval_condition(condition, context)? {
+            return Ok(Object::Null);
+        }
+        match eval(body, context) {
+            Ok(_) | Err(Unwind::Continue(_)) => {}
+            Err(Unwind::Break(value, _)) => return Ok(value),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn eval_for(
+    (name, name_span): &(String, Span),
+    iterable: &AstNode,
+    body: &AstNode,
+    span: Span,
+    context: &Context,
+) -> Result<Object, Unwind> {
+    let iter = eval(iterable, context)?
+        .iter()
+        .map_err(|err| err.with_span(iterable.span))?;
+    loop {
+        let item = match iter.next().map_err(|err| err.with_span(span))? {
+            Some(item) => item,
+            None => return Ok(Object::Null),
+        };
+        let scope = Context::with_parent(context);
+        scope.set(name.clone(), &item, Declaration::Let, *name_span);
+        match eval(body, &scope) {
+            Ok(_) | Err(Unwind::Continue(_)) => {}
+            Err(Unwind::Break(value, _)) => return Ok(value),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn eval_in(
+    item: &AstNode,
+    container: &AstNode,
+    span: Span,
+    context: &Context,
+) -> Result<Object, Unwind> {
+    let item = eval(item, context)?;
+    let container = eval(container, context)?;
+    let contains = container
+        .contains(&item)
+        .map_err(|err| err.with_span(span))?;
+    Ok(Object::Bool(contains))
+}
+
+fn eval_short_circuit(
+    left: &AstNode,
+    op: Operator,
+    right: &AstNode,
+    context: &Context,
+) -> Result<Object, Unwind> {
+    Ok(match op {
+        AmpersandAmpersand if !eval_condition(left, context)? => Object::Bool(false),
+        PipePipe if eval_condition(left, context)? => Object::Bool(true),
+        AmpersandAmpersand | PipePipe => Object::Bool(eval_condition(right, context)?),
+        QuestionQuestion => match eval(left, context)? {
+            Object::Null => eval(right, context)?,
+            left => left,
+        },
+        _ => unreachable!(),
+    })
+}
+
+fn eval_fn_decl(
+    name: &Option<(String, Span)>,
+    params: &[(String, Span)],
+    body: &Rc<AstNode>,
+    span: Span,
+    context: &Context,
+) -> Object {
+    let function = Object::Function(Rc::new(Function {
+        name: name.as_ref().map(|(name, _)| name.clone()),
+        params: params.to_vec(),
+        body: body.clone(),
+        env: context.clone(),
+        span,
+    }));
+    if let Some((name, name_span)) = name {
+        context.set(name.clone(), &function, Declaration::Let, *name_span);
+    }
+    function
+}
+
+/// Value of `return` or `break`, `null` if there's none.
+fn eval_optional(value: &Option<Box<AstNode>>, context: &Context) -> Result<Object, Unwind> {
+    match value {
+        Some(value) => eval(value, context),
+        None => Ok(Object::Null),
+    }
+}
+
+fn eval_call(
+    callee: &AstNode,
+    args: &[AstNode],
+    span: Span,
+    context: &Context,
+) -> Result<Object, Unwind> {
+    let callee = eval(callee, context)?;
+    let args = eval_items(args, context)?;
+    Ok(call(&callee, args).map_err(|err| err.with_span(span))?)
+}
+
+fn eval_method_call(
+    receiver: &AstNode,
+    name: &str,
+    args: &[AstNode],
+    optional: bool,
+    span: Span,
+    context: &Context,
+) -> Result<Object, Unwind> {
+    let receiver = eval(receiver, context)?;
+    if optional && matches!(receiver, Object::Null) {
+        return Ok(Object::Null);
+    }
+    let args = eval_items(args, context)?;
+    Ok(receiver
+        .call_method(name, &args)
+        .map_err(|err| err.with_span(span))?)
+}
+
+fn eval_items(items: &[AstNode], context: &Context) -> Result<Vec<Object>, Unwind> {
+    items.iter().map(|item| eval(item, context)).collect()
+}
+
+fn eval_set(items: &[AstNode], context: &Context) -> Result<Object, Unwind> {
+    let mut set = Set::new();
+    for item in items {
+        let item_span = item.span;
+        let item = eval(item, context)?;
+        set.insert(item).map_err(|err| err.with_span(item_span))?;
+    }
+    Ok(Object::set(set))
+}
+
+fn eval_map(entries: &[(AstNode, AstNode)], context: &Context) -> Result<Object, Unwind> {
+    let mut map = Map::new();
+    for (key, value) in entries {
+        let key_span = key.span;
+        let key = eval(key, context)?;
+        let value = eval(value, context)?;
+        map.insert(key, value)
+            .map_err(|err| err.with_span(key_span))?;
+    }
+    Ok(Object::map(map))
+}
+
+fn eval_index(
+    container: &AstNode,
+    index: &AstNode,
+    span: Span,
+    context: &Context,
+) -> Result<Object, Unwind> {
+    let container = eval(container, context)?;
+    let index = eval(index, context)?;
+    Ok(container.idx(&index).map_err(|err| err.with_span(span))?)
+}
+
+/// Evaluates the body of the first arm that matches `value` and whose guard
+/// holds. The guard is tried with the bindings of every alternative of the
+/// arm's pattern that matches, alternatives nested deeper in the pattern only
+/// get one try.
+fn eval_match(
+    value_node: &AstNode,
+    arms: &[MatchArm],
+    context: &Context,
+) -> Result<Object, Unwind> {
+    let value = eval(value_node, context)?;
+    for arm in arms {
+        let alternatives = match &arm.pattern.kind {
+            PatternKind::Or(alternatives) => alternatives.iter().collect(),
+            _ => vec![&arm.pattern],
+        };
+        for pattern in alternatives {
+            let mut bindings = vec![];
+            match match_pattern(pattern, &value, context, &mut bindings) {
+                Ok(()) => {}
+                Err(RuntimeError {
+                    kind: RuntimeErrorKind::PatternMismatch { .. },
+                    ..
+                }) => continue,
+                Err(err) => return Err(err.into()),
+            }
+            let scope = Context::with_parent(context);
+            for (name, value, span) in bindings {
+                scope.set(name, &value, Declaration::Let, span);
+            }
+            if let Some(guard) = &arm.guard {
+                if !eval_condition(guard, &scope)? {
+                    continue;
+                }
+            }
+            return eval(&arm.body, &scope);
+        }
+    }
+    Err(error_at(
+        RuntimeErrorKind::NoMatch(value.repr()),
+        value_node.span,
+    ))
+}
+
+/// Matches `value` against `pattern`, pushing the names it binds to `bindings`
+/// along with the span of the binding pattern. If it doesn't match, the error
+/// points at the part of the pattern that failed.
+fn match_pattern(
+    pattern: &Pattern,
+    value: &Object,
+    context: &Context,
+    bindings: &mut Vec<(String, Object, Span)>,
+) -> Result<(), RuntimeError> {
+    let mismatch = |expected: String| {
+        RuntimeError::new(RuntimeErrorKind::PatternMismatch {
+            expected,
+            found: value.repr(),
+        })
+        .with_span(pattern.span)
+    };
+    // literals never fail to evaluate
+    let literal = |node| eval(node, context).map_err(Unwind::into_error);
+    match &pattern.kind {
+        PatternKind::Wildcard => {}
+        PatternKind::Binding(name) => bindings.push((name.clone(), value.clone(), pattern.span)),
+        PatternKind::Literal(node) => {
+            let expected = literal(node)?;
+            if expected != *value {
+                return Err(mismatch(expected.repr()));
+            }
+        }
+        PatternKind::Range(start, end, inclusive) => {
+            let (start, end) = (literal(start)?, literal(end)?);
+            let after_start = matches!(
+                value.partial_cmp(&start),
+                Some(Ordering::Greater | Ordering::Equal)
+            );
+            let before_end = match value.partial_cmp(&end) {
+                Some(Ordering::Less) => true,
+                Some(Ordering::Equal) => *inclusive,
+                _ => false,
+            };
+            if !(after_start && before_end) {
+                let op = if *inclusive { "..=" } else { ".." };
+                return Err(mismatch(format!(
+                    "a value in {}{}{}",
+                    start.repr(),
+                    op,
+                    end.repr()
+                )));
+            }
+        }
+        PatternKind::Or(alternatives) => {
+            let bound = bindings.len();
+            for alternative in alternatives {
+                match match_pattern(alternative, value, context, bindings) {
+                    Ok(()) => return Ok(()),
+                    Err(RuntimeError {
+                        kind: RuntimeErrorKind::PatternMismatch { .. },
+                        ..
+                    }) => bindings.truncate(bound),
+                    Err(err) => return Err(err),
+                }
+            }
+            return Err(mismatch("one of the alternatives".to_string()));
+        }
+        PatternKind::Tuple(patterns) => match value {
+            Object::Tuple(items) if items.len() == patterns.len() => {
+                for (pattern, item) in patterns.iter().zip(items.iter()) {
+                    match_pattern(pattern, item, context, bindings)?;
+                }
+            }
+            _ => return Err(mismatch(format!("a Tuple of {}", items(patterns.len())))),
+        },
+        PatternKind::List(patterns, rest) => {
+            let list = match value {
+                Object::List(list) => list.borrow().clone(),
+                _ => vec![],
+            };
+            let fits = match rest {
+                Some(_) => list.len() >= patterns.len(),
+                None => list.len() == patterns.len(),
+            };
+            if !matches!(value, Object::List(_)) || !fits {
+                let at_least = if rest.is_some() { "at least " } else { "" };
+                return Err(mismatch(format!(
+                    "a List of {}{}",
+                    at_least,
+                    items(patterns.len())
+                )));
+            }
+            // items before the rest pattern match the start of the list, the
+            // ones after it match the end
+            let split = rest.as_ref().map_or(patterns.len(), |(index, _)| *index);
+            let tail = list.len() - (patterns.len() - split);
+            for (pattern, item) in patterns[..split].iter().zip(&list[..split]) {
+                match_pattern(pattern, item, context, bindings)?;
+            }
+            if let Some((_, rest)) = rest {
+                let middle = Object::list(list[split..tail].to_vec());
+                match_pattern(rest, &middle, context, bindings)?;
+            }
+            for (pattern, item) in patterns[split..].iter().zip(&list[tail..]) {
+                match_pattern(pattern, item, context, bindings)?;
+            }
+        }
+        PatternKind::Map(entries) => {
+            let map = match value {
+                Object::Map(map) => map.borrow().clone(),
+                _ => return Err(mismatch("a Map".to_string())),
+            };
+            for (key, pattern) in entries {
+                let key = Object::String(Rc::new(RefCell::new(key.clone())));
+                match map.get(&key)? {
+                    Some(item) => match_pattern(pattern, item, context, bindings)?,
+                    None => {
+                        return Err(RuntimeError::new(RuntimeErrorKind::PatternMismatch {
+                            expected: format!("a Map with the key {}", key.repr()),
+                            found: value.repr(),
+                        })
+                        .with_span(pattern.span))
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Assigns the items of `value` to the targets of a `(a, b) = value` or
+/// `[a, b] = value` assignment, or `value` itself to a single target.
+fn assign_destructured(target: &AstNode, value: Object, context: &Context) -> Result<(), Unwind> {
+    let span = target.span;
+    match &target.kind {
+        AstKind::VarAccess(name) => context
+            .assign(name, &value)
+            .map_err(|kind| RuntimeError::new(kind).with_span(span))?,
+        AstKind::Index(container, index) => {
+            let container = eval(container, context)?;
+            let index = eval(index, context)?;
+            container
+                .set_idx(&index, value)
+                .map_err(|err| err.with_span(span))?
+        }
+        AstKind::Tuple(targets) | AstKind::List(targets) => {
+            let (values, r#type) = match (&target.kind, &value) {
+                (AstKind::Tuple(_), Object::Tuple(items)) => (Some((**items).clone()), "Tuple"),
+                (AstKind::List(_), Object::List(items)) => (Some(items.borrow().clone()), "List"),
+                (AstKind::Tuple(_), _) => (None, "Tuple"),
+                _ => (None, "List"),
+            };
+            let values = match values {
+                Some(values) if values.len() == targets.len() => values,
+                _ => {
+                    return Err(Unwind::Error(
+                        RuntimeError::new(RuntimeErrorKind::PatternMismatch {
+                            expected: format!("a {} of {}", r#type, items(targets.len())),
+                            found: value.repr(),
+                        })
+                        .with_span(span),
+                    ))
+                }
+            };
+            for (target, item) in targets.iter().zip(values) {
+                assign_destructured(target, item, context)?;
+            }
+        }
+        _ => unreachable!("the parser only allows assignable targets"),
+    }
+    Ok(())
+}
+
+/// `n item` or `n items`.
+fn items(n: usize) -> String {
+    format!("{} item{}", n, if n == 1 { "" } else { "s" })
+}
+
+/// Applies the binary operator `op`, as in `BinOp` and compound assignments.
+fn binary_op(left: &Object, op: Operator, right: &Object) -> Result<Object, RuntimeError> {
+    match op {
+        Plus => left.add(right),
+        Minus => left.sub(right),
+        Star => left.mul(right),
+        Slash => left.div(right),
+        StarStar => left.pow(right),
+        Percent => left.rem(right),
+        Pipe => left.bitor(right),
+        Ampersand => left.bitand(right),
+        Caret => left.bitxor(right),
+        GreaterGreater => left.shr(right),
+        LessLess => left.shl(right),
+        EqualsEquals => Ok(left.eq(right)),
+        ExclamationEquals => Ok(left.ne(right)),
+        Greater => left.gt(right),
+        Less => left.lt(right),
+        GreaterEquals => left.gte(right),
+        LessEquals => left.lte(right),
+        DotDot => Object::range(left, right, false),
+        DotDotEquals => Object::range(left, right, true),
+        _ => todo!(),
+    }
+}
+
+/// Makes sure `node` can be evaluated without running user code or depending
+/// on mutable state: only literals, operators and other constants are allowed.
+fn check_constant(node: &AstNode, context: &Context) -> Result<(), RuntimeError> {
+    match &node.kind {
+        AstKind::Number(_)
+        | AstKind::Int(_)
+        | AstKind::BigInt(_)
+        | AstKind::Bool(_)
+        | AstKind::Char(_)
+        | AstKind::String(_)
+        | AstKind::Null => Ok(()),
+        AstKind::UnOp(_, operand) => check_constant(operand, context),
+        AstKind::If(condition, then, Some(otherwise)) => {
+            check_constant(condition, context)?;
+            check_constant(then, context)?;
+            check_constant(otherwise, context)
+        }
+        AstKind::BinOp(left, _, right)
+        | AstKind::In(left, right)
+        | AstKind::ShortCircuit(left, _, right) => {
+            check_constant(left, context)?;
+            check_constant(right, context)
+        }
+        // tuples are immutable, so a tuple of constants is constant too
+        AstKind::Tuple(items) => items
+            .iter()
+            .try_for_each(|item| check_constant(item, context)),
+        AstKind::VarAccess(name) => match context.declaration(name) {
+            Some((_, Declaration::Const)) | None => Ok(()),
+            Some(_) => Err(RuntimeError::new(RuntimeErrorKind::NotConstant).with_span(node.span)),
+        },
+        _ => Err(RuntimeError::new(RuntimeErrorKind::NotConstant).with_span(node.span)),
+    }
+}
+
+/// Evaluates statements one after another, yielding the value of the last one.
+fn eval_statements(nodes: &[AstNode], context: &Context) -> Result<Object, Unwind> {
+    let mut last = Object::Null;
+    for node in nodes {
+        last = eval(node, context)?;
+    }
+    Ok(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{lexer::lex, parser::parse, span::Position};
+
+    fn run(source: &str) -> Result<Object, RuntimeError> {
+        let ast = parse(lex(source, Position::default()).unwrap()).unwrap();
+        visit(&ast, &Context::new())
+    }
+
+    fn undefined(name: &str) -> RuntimeErrorKind {
+        RuntimeErrorKind::UndefinedName(name.to_string())
+    }
+
+    #[test]
+    fn block_sees_outer_variables() {
+        assert_eq!(run("let x = 1; { { x + 1 } }"), Ok(Object::Int(2)));
+    }
+
+    #[test]
+    fn let_in_block_shadows_outer_variable() {
+        assert_eq!(run("let x = 1; { let x = 2; x }"), Ok(Object::Int(2)));
+        assert_eq!(run("let x = 1; { let x = 2 }; x"), Ok(Object::Int(1)));
+    }
+
+    #[test]
+    fn nested_shadowing() {
+        let source = "
+            let x = 1
+            let mut inner = 0
+            let mut middle = 0
+            {
+                let x = x + 10
+                {
+                    let x = x + 100
+                    inner = x
+                }
+                middle = x
+            }
+            x * 10000 + middle * 100 + inner
+        ";
+        assert_eq!(run(source), Ok(Object::Int(11211)));
+    }
+
+    #[test]
+    fn let_shadows_in_same_scope() {
+        assert_eq!(run("let x = 1; let x = x + 1; x"), Ok(Object::Int(2)));
+    }
+
+    #[test]
+    fn block_variables_do_not_leak() {
+        let err = run("{ let y = 1 }; y").unwrap_err();
+        assert_eq!(err.kind, undefined("y"));
+    }
+
+    #[test]
+    fn assignment_updates_outer_variable() {
+        assert_eq!(run("let mut x = 1; { x = 2 }; x"), Ok(Object::Int(2)));
+        assert_eq!(
+            run("let mut x = 1; { { { x = 3 } } }; x"),
+            Ok(Object::Int(3))
+        );
+    }
+
+    #[test]
+    fn assignment_updates_nearest_definition() {
+        assert_eq!(
+            run("let x = 1; { let mut x = 2; { x = 3 }; x }"),
+            Ok(Object::Int(3))
+        );
+        assert_eq!(
+            run("let mut x = 1; { let mut x = 2; x = 3 }; x"),
+            Ok(Object::Int(1))
+        );
+    }
+
+    #[test]
+    fn assignment_never_declares() {
+        let err = run("{ z = 1 }").unwrap_err();
+        assert_eq!(err.kind, undefined("z"));
+    }
+
+    #[test]
+    fn loops_and_functions_assign_outer_variables() {
+        assert_eq!(
+            run("let mut s = 0; for i in 1..=4 { let t = i * 2; s = s + t }; s"),
+            Ok(Object::Int(20))
+        );
+        assert_eq!(
+            run("let mut n = 0; fn bump() { n = n + 1 }; bump(); bump(); n"),
+            Ok(Object::Int(2))
+        );
+    }
+
+    #[test]
+    fn let_bindings_are_immutable() {
+        let err = run("let x = 1; { x = 2 }").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            RuntimeErrorKind::AssignToImmutable {
+                constant: false,
+                ..
+            }
+        ));
+        let err = run("const X = 1; X = 2").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            RuntimeErrorKind::AssignToImmutable { constant: true, .. }
+        ));
+    }
+
+    #[test]
+    fn const_needs_constant_expression() {
+        assert_eq!(
+            run("const A = 2 ** 10; const B = -A + 1; B"),
+            Ok(Object::Int(-1023))
+        );
+        let err = run("let a = 1; const B = a").unwrap_err();
+        assert_eq!(err.kind, RuntimeErrorKind::NotConstant);
+    }
+
+    #[test]
+    fn compound_assignment_evaluates_target_once() {
+        let source = "
+            let mut calls = 0
+            let xs = [1, 2]
+            fn index() { calls += 1; 1 }
+            xs[index()] *= 10
+            (xs, calls)
+        ";
+        assert_eq!(
+            run(source),
+            Ok(Object::tuple(vec![
+                Object::list(vec![Object::Int(1), Object::Int(20)]),
+                Object::Int(1)
+            ]))
+        );
+        let err = run("let x = 1; x += 1").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            RuntimeErrorKind::AssignToImmutable { .. }
+        ));
+    }
+
+    #[test]
+    fn destructuring() {
+        let source = "
+            let (a, [b, ..rest], {c}) = (1, [2, 3, 4], {\"c\": 5})
+            let mut x = a
+            let mut y = c
+            (x, y) = (y, x)
+            (b, rest, x, y)
+        ";
+        assert_eq!(
+            run(source),
+            Ok(Object::tuple(vec![
+                Object::Int(2),
+                Object::list(vec![Object::Int(3), Object::Int(4)]),
+                Object::Int(5),
+                Object::Int(1)
+            ]))
+        );
+        let err = run("let (a, [b, 2]) = (1, [2, 3])").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            RuntimeErrorKind::PatternMismatch { ref expected, .. } if expected == "2"
+        ));
+    }
+
+    #[test]
+    fn newlines_inside_brackets_continue_the_expression() {
+        let source = "
+            let x = (1 +
+                2)
+            let xs = [
+                x,
+                4
+            ]
+            let f = |n| {
+                let m = n
+                m * 2
+            }
+            f(
+                xs[1]
+            )
+        ";
+        assert_eq!(run(source), Ok(Object::Int(8)));
+    }
+
+    #[test]
+    fn bitwise_operators_never_truncate_floats() {
+        assert_eq!(run("3.0 & 1"), Ok(Object::Int(1)));
+        assert_eq!(run("2.0 ** 62 | 1"), Ok(Object::Int((1 << 62) | 1)));
+        for source in ["1.5 & 1", "1e300 | 1", "5 >> 0.5", "(0.0 / 0) ^ 1"] {
+            let err = run(source).unwrap_err();
+            assert!(
+                matches!(err.kind, RuntimeErrorKind::InvalidValue(_)),
+                "{}",
+                source
+            );
+        }
+        let err = run("1 >> 2 ** 100").unwrap_err();
+        assert_eq!(
+            err.kind,
+            RuntimeErrorKind::InvalidValue(
+                "Operator '>>' can't shift by 1267650600228229401496703205376 bits, the amount \
+                 is out of range"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn not_agrees_with_conditions() {
+        for value in [
+            "0", "1", "0.0 / 0", "\"\"", "\"a\"", "'\\0'", "[]", "[0]", "0..0", "{}",
+        ] {
+            let source = format!("(!{0}, if {0} {{ false }} else {{ true }})", value);
+            match run(&source) {
+                Ok(Object::Tuple(items)) => assert_eq!(items[0], items[1], "{}", value),
+                result => panic!("{}: {:?}", value, result),
+            }
+        }
+    }
+
+    #[test]
+    fn iterators_are_values() {
+        let source = "
+            let it = [1, 2, 3].iter()
+            let first = it.next()
+            let mut rest = 0
+            for x in it { rest += x }
+            let mut n = 0
+            let count = || { n += 1; if n <= 3 { n } else { null } }
+            let counted = []
+            for i in count.iter() { counted.push(i) }
+            (first, rest, it.next(), counted)
+        ";
+        assert_eq!(
+            run(source),
+            Ok(Object::tuple(vec![
+                Object::Int(1),
+                Object::Int(5),
+                Object::Null,
+                Object::list(vec![Object::Int(1), Object::Int(2), Object::Int(3)])
+            ]))
+        );
+        let err = run("5.iter()").unwrap_err();
+        assert_eq!(err.kind, RuntimeErrorKind::NotIterable("Int".to_string()));
+    }
+
+    #[test]
+    fn recursion_limit() {
+        let context = Context::with_recursion_limit(20);
+        let run = |source| {
+            let ast = parse(lex(source, Position::default()).unwrap()).unwrap();
+            visit(&ast, &context)
+        };
+        run("fn sum(n) { if n == 0 { 0 } else { n + sum(n - 1) } }").unwrap();
+        assert_eq!(run("sum(19)"), Ok(Object::Int(190)));
+        let err = run("sum(20)").unwrap_err();
+        assert_eq!(err.kind, RuntimeErrorKind::RecursionLimit(20));
+        // the calls that failed don't count any more
+        assert_eq!(run("sum(19)"), Ok(Object::Int(190)));
+    }
+
+    #[test]
+    fn params_are_declared_at_their_own_span() {
+        let err = run("fn f(a, b) { b = 1 }; f(1, 2)").unwrap_err();
+        match err.kind {
+            RuntimeErrorKind::AssignToImmutable { declared, .. } => {
+                assert_eq!(declared.start.column, 9)
+            }
+            kind => panic!("{:?}", kind),
+        }
+    }
+
+    #[test]
+    fn function_name_is_declared_at_its_own_span() {
+        let err = run("fn f(){1}; f = 2").unwrap_err();
+        match err.kind {
+            RuntimeErrorKind::AssignToImmutable { declared, .. } => {
+                assert_eq!((declared.start.column, declared.end.column), (4, 5))
+            }
+            kind => panic!("{:?}", kind),
+        }
+    }
+
+    #[test]
+    fn loop_variable_is_declared_at_its_own_span() {
+        let err = run("for i in 0..3 { i = 5 }").unwrap_err();
+        match err.kind {
+            RuntimeErrorKind::AssignToImmutable { declared, .. } => {
+                assert_eq!((declared.start.column, declared.end.column), (5, 6))
+            }
+            kind => panic!("{:?}", kind),
+        }
+    }
+
+    #[test]
+    fn lists() {
+        let source = "
+            let xs = [1, 2, 3,]
+            let ys = xs
+            ys[-1] = 30
+            xs[0] += 9
+            (xs[1], xs[-3], xs[1..3], xs[0..-1], xs[0..=1], xs[3..3], ys)
+        ";
+        assert_eq!(
+            run(source),
+            run("(2, 10, [2, 30], [10, 2], [10, 2], [], [10, 2, 30])")
+        );
+        for (source, index) in [
+            ("[1, 2][2]", 2),
+            ("[1, 2][-3]", -3),
+            ("[1, 2][-3..1]", -3),
+            ("[1, 2][0..3]", 3),
+            ("[1, 2][0..=2]", 2),
+            ("let xs = [1, 2]; xs[2] = 0", 2),
+        ] {
+            let err = run(source).unwrap_err();
+            assert_eq!(
+                err.kind,
+                RuntimeErrorKind::IndexOutOfRange { index, len: 2 },
+                "{}",
+                source
+            );
+        }
+        let err = run("[1, 2][\"0\"]").unwrap_err();
+        assert!(matches!(err.kind, RuntimeErrorKind::TypeMismatch { .. }));
+        let err = run("5[0]").unwrap_err();
+        assert_eq!(err.kind, RuntimeErrorKind::NotIndexable("Int".to_string()));
+    }
+
+    #[test]
+    fn maps() {
+        let source = "
+            let m = {\"b\": 1, 2: \"x\",}
+            m[\"a\"] = 3
+            m[2] = \"y\"
+            m[2.0] += \"z\"
+            let keys = []
+            for k in m { keys.push(k) }
+            (m[\"b\"], m[2], \"a\" in m, \"z\" in m, keys, {1: 0, 1.0: 1})
+        ";
+        assert_eq!(
+            run(source),
+            run("(1, \"yz\", true, false, [\"b\", 2, \"a\"], {1: 1})")
+        );
+        let err = run("{\"a\": 1}[\"b\"]").unwrap_err();
+        assert_eq!(err.kind, RuntimeErrorKind::KeyNotFound("\"b\"".to_string()));
+        let err = run("{[1]: 2}").unwrap_err();
+        assert_eq!(err.kind, RuntimeErrorKind::Unhashable("List".to_string()));
+        let err = run("let m = {}; m[0.0 / 0] = 1").unwrap_err();
+        assert!(matches!(err.kind, RuntimeErrorKind::InvalidValue(_)));
+    }
+
+    #[test]
+    fn tuples_and_sets() {
+        let source = "
+            let t = (1, \"a\")
+            (t[-1], (1,), t < (1, \"b\"), (1, 2) < (1, 2, 0), (2,) > (1, 9))
+        ";
+        assert_eq!(run(source), run("(\"a\", (1,), true, true, true)"));
+        let err = run("let t = (1, 2); t[0] = 3").unwrap_err();
+        assert_eq!(
+            err.kind,
+            RuntimeErrorKind::ItemAssignment("Tuple".to_string())
+        );
+
+        let source = "
+            let a = {1, 2}
+            let b = {2, 3}
+            (a | b, a & b, a ^ b, a - b, {,}, 2 in a, {1, 1.0})
+        ";
+        assert_eq!(
+            run(source),
+            run("({1, 2, 3}, {2,}, {1, 3}, {1,}, {,}, true, {1,})")
+        );
+        let err = run("{[1], 2}").unwrap_err();
+        assert_eq!(err.kind, RuntimeErrorKind::Unhashable("List".to_string()));
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        let source = "
+            let mut calls = 0
+            fn hit() { calls += 1; true }
+            (false && hit(), true || hit(), 1 ?? hit(), true && hit(), false || hit(), calls)
+        ";
+        assert_eq!(run(source), run("(false, true, 1, true, true, 2)"));
+        assert_eq!(
+            run("(null ?? 1, 0 ?? 1, false ?? 1, null ?? null ?? 2)"),
+            run("(1, 0, false, 2)")
+        );
+        assert_eq!(
+            run("(true || false && false, 1 < 2 && 2 < 3, null ?? false || true)"),
+            run("(true, true, true)")
+        );
+    }
+
+    #[test]
+    fn null_is_never_coerced() {
+        let source = "
+            fn nothing() {}
+            let m = {\"a\": [1]}
+            (nothing(), if false { 1 }, null ?? 1, 0 ?? 1, null == null, null != 0,
+             m.get(\"b\"), m.get(\"b\")?.len(), m.get(\"a\")?.len())
+        ";
+        assert_eq!(
+            run(source),
+            run("(null, null, 1, 0, true, true, null, null, 1)")
+        );
+        for source in [
+            "if null { 1 }",
+            "!null",
+            "null && true",
+            "true && null",
+            "false || null",
+            "null ? 1 : 2",
+            "while null {}",
+            "match 1 { _ if null => 1 }",
+        ] {
+            let err = run(source).unwrap_err();
+            assert_eq!(err.kind, RuntimeErrorKind::NullCondition, "{}", source);
+        }
+        for source in ["null + 1", "-null", "null < 1", "null[0]"] {
+            assert!(run(source).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn match_and_conditional_expressions() {
+        let source = "
+            fn f(v) {
+                match v {
+                    0 => \"zero\",
+                    1 | 2 => \"small\",
+                    3..=9 => \"digit\",
+                    \"a\" | 'b' => \"letter\",
+                    n if n < 0 => \"negative\",
+                    (x, [y, ..]) | (y, x) => x + y,
+                    _ => \"other\",
+                }
+            }
+            (f(0), f(2.0), f(5), f('b'), f(-3), f((1, [10])), f((4, 5)), f(50), 1 < 2 ? 1 : 0 ? 2 : 3)
+        ";
+        assert_eq!(
+            run(source),
+            run("(\"zero\", \"small\", \"digit\", \"letter\", \"negative\", 11, 9, \"other\", 1)")
+        );
+        let err = run("match (1, 2) { (a, b) if a > b => 1, 3 | 4 => 2 }").unwrap_err();
+        assert_eq!(err.kind, RuntimeErrorKind::NoMatch("(1, 2)".to_string()));
+    }
+
+    #[test]
+    fn alternatives_only_skip_mismatches() {
+        let span = Span::default();
+        let missing = AstNode::new(AstKind::VarAccess("missing".to_string()), span);
+        let alternatives = vec![
+            Pattern {
+                kind: PatternKind::Literal(missing),
+                span,
+            },
+            Pattern {
+                kind: PatternKind::Wildcard,
+                span,
+            },
+        ];
+        let pattern = Pattern {
+            kind: PatternKind::Or(alternatives),
+            span,
+        };
+        let err =
+            match_pattern(&pattern, &Object::Int(1), &Context::new(), &mut vec![]).unwrap_err();
+        assert_eq!(err.kind, undefined("missing"));
+    }
+
+    #[test]
+    fn lists_containing_themselves() {
+        let source = "let l = [1]; l.push(l); l";
+        assert_eq!(run(source).unwrap().repr(), "[1, [...]]");
+        let source = "let l = []; l.push((l,)); l";
+        assert_eq!(run(source).unwrap().repr(), "[([...],)]");
+        let source = "
+            let l = [1]
+            l.push(l)
+            let k = [1]
+            k.push(k)
+            let j = [2]
+            j.push(j)
+            (l == l, l == k, l == [1, l], l in [0, l], l == j)
+        ";
+        assert_eq!(run(source), run("(true, true, true, true, false)"));
+    }
+
+    #[test]
+    fn maps_containing_themselves() {
+        let source = "let m = {}; m[\"self\"] = m; m";
+        assert_eq!(run(source).unwrap().repr(), "{\"self\": {...}}");
+        let mutual = "
+            let a = {}
+            let b = {}
+            a[\"x\"] = b
+            b[\"x\"] = a
+        ";
+        let source = format!("{}; a", mutual);
+        assert_eq!(run(&source).unwrap().repr(), "{\"x\": {\"x\": {...}}}");
+        let source = format!(
+            "{}; let c = {{\"x\": 1}}; c[\"y\"] = c; (a == a, a == b, a == {{\"x\": a}}, a == c)",
+            mutual
+        );
+        assert_eq!(run(&source), run("(true, true, true, false)"));
+    }
+
+    #[test]
+    fn bitwise_operators_on_big_ints() {
+        let source = "
+            let big = 2 ** 64
+            (big | 1, big & 1, (big + 5) & 7, -big | 1, -big ^ -1, big ^ big, big | 1.0, big | true)
+        ";
+        assert_eq!(
+            run(source),
+            run("(18446744073709551617, 0, 5, -18446744073709551615, 18446744073709551615, 0, 18446744073709551617, 18446744073709551617)")
+        );
+        let err = run("2 ** 64 | 0.5").unwrap_err();
+        assert!(matches!(err.kind, RuntimeErrorKind::InvalidValue(_)));
+    }
+
+    #[test]
+    fn char_arithmetic_stays_in_range() {
+        assert_eq!(
+            run(
+                "(1 + 'a', 'a' + 1, 'a' + 1.0, 'b' - 1, 'b' - 'a' + 'a', 200 + 'a', 100.0 - '\\0')"
+            ),
+            run("('b', 'b', 'b', 'a', 'b', '\u{129}', 'd')")
+        );
+        for source in [
+            "'a' - 'b'",
+            "'a' - 98",
+            "0x10FFFF + 'a'",
+            "0xD800 + '\\0'",
+            "9223372036854775807 + 'a'",
+            "'a' + 0.5",
+        ] {
+            let err = run(source).unwrap_err();
+            assert!(
+                matches!(err.kind, RuntimeErrorKind::InvalidValue(_)),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn loops_break_and_continue() {
+        let source = "
+            let mut i = 0
+            let counted = while i < 3 { i += 1 }
+            let mut j = 0
+            let found = while true { j += 1; if j == 4 { break j * 10 } }
+            let square = for x in 1..10 { if x * x > 20 { break x } }
+            let evens = []
+            for x in 0..6 { if x % 2 == 1 { continue }; evens.push(x) }
+            let mut k = 0
+            let seen = []
+            while k < 5 { k += 1; if k == 2 { continue }; seen.push(k) }
+            (counted, i, found, square, for x in 1..3 { break }, evens, seen)
+        ";
+        assert_eq!(
+            run(source),
+            run("(null, 3, 40, 5, null, [0, 2, 4], [1, 3, 4, 5])")
+        );
+        for (source, keyword) in [
+            ("break", "break"),
+            ("break 1", "break"),
+            ("continue", "continue"),
+            ("fn f() { break }; while true { f() }", "break"),
+        ] {
+            let err = run(source).unwrap_err();
+            assert_eq!(
+                err.kind,
+                RuntimeErrorKind::OutsideLoop(keyword.to_string()),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn range_steps_and_membership() {
+        let source = "
+            fn collect(range) {
+                let items = []
+                for x in range { items.push(x) }
+                items
+            }
+            (
+                collect((0..10).step(3)),
+                collect((0..=9).step(3)),
+                collect((10..0).step(-3)),
+                collect((0..=10).step(-2)),
+                collect((0..5).rev()),
+                collect((0..10).step(4).rev()),
+                collect((5..0).rev()),
+            )
+        ";
+        assert_eq!(
+            run(source),
+            run("([0, 3, 6, 9], [0, 3, 6, 9], [10, 7, 4, 1], [], [4, 3, 2, 1, 0], [8, 4, 0], [])")
+        );
+        let source = "(3 in 0..5, 5 in 0..5, 5 in 0..=5, 6 in (0..10).step(3), 4 in (0..10).step(3), 1.0 in 0..3, 1.5 in 0..3, 4 in (10..0).step(-3), 0 in (10..0).step(-3))";
+        assert_eq!(
+            run(source),
+            run("(true, false, true, true, false, true, false, true, false)")
+        );
+        let source = "((0..10).step(3), (0..=10).step(-2), 0..3, 0..=3, (0..5).rev())";
+        assert_eq!(
+            run(source).unwrap().repr(),
+            "((0..10).step(3), (0..=10).step(-2), 0..3, 0..=3, (4..=0).step(-1))"
+        );
+        let err = run("(0..10).step(0)").unwrap_err();
+        assert!(matches!(err.kind, RuntimeErrorKind::InvalidValue(_)));
+        let err = run("(0..10).step(-9223372036854775807 - 1)").unwrap_err();
+        assert!(matches!(err.kind, RuntimeErrorKind::IntegerOverflow(_)));
+        let err = run("(0..10).step(1.5)").unwrap_err();
+        assert!(matches!(err.kind, RuntimeErrorKind::InvalidValue(_)));
+    }
+
+    #[test]
+    fn closures_outlive_their_scope() {
+        let source = "
+            fn make() {
+                let mut n = 0
+                || { n += 1; n }
+            }
+            let a = make()
+            let b = make()
+            (a(), a(), b(), a(), b())
+        ";
+        assert_eq!(run(source), run("(1, 2, 1, 3, 2)"));
+        let err = run("fn make() { let mut n = 0; || n }; make()(); n").unwrap_err();
+        assert_eq!(err.kind, undefined("n"));
+    }
+
+    #[test]
+    fn guards_are_tried_for_each_alternative() {
+        let source = "
+            let mut tries = 0
+            fn check(a) { tries += 1; a > 1 }
+            let swapped = match (1, 2) { (a, b) | (b, a) if check(a) => a, _ => 0 }
+            let neither = match (1, 2) { (a, b) | (b, a) if a > 5 => a, _ => 0 }
+            // nested alternatives bind once, with the first that matches
+            let nested = match ((1, 2), 0) { ((a, b) | (b, a), _) if a > 1 => a, _ => 0 }
+            (swapped, tries, neither, nested)
+        ";
+        assert_eq!(run(source), run("(2, 2, 0, 0)"));
+    }
+}
@@ -1,211 +1,608 @@
-use std::{iter::Peekable, str::Chars};
-
-use super::tokens::{
-    Operator::{self, *},
-    Parenthesis, Token,
-};
-
-pub fn lex(source: &str) -> Result<Vec<Token>, String> {
-    let mut tokens = vec![];
-    let mut source: Peekable<Chars> = source.chars().peekable();
-
-    while let Some(ch) = source.peek() {
-        match ch {
-            '0'..='9' | '.' => {
-                tokens.push(Token::Number(make_number(&mut source)?));
-            }
-            'a'..='z' | 'A'..='Z' | '_' | '$' => {
-                tokens.push(Token::Ident(make_name(&mut source)));
-            }
-            '=' | '+' | '-' | '*' | '/' | '%' | '!' | '>' | '<' | ':' | ';' | '(' | ')' | '['
-            | ']' | '{' | '}' | '|' | '^' | '&' => {
-                tokens.push(Token::Operator(make_operator(&mut source)));
-            }
-            ' ' | '\n' | '\r' | '\t' => {
-                source.next();
-            }
-            '"' => {
-                tokens.push(Token::String(make_string(&mut source)?));
-            }
-            '\'' => {
-                tokens.push(Token::Char(make_char(&mut source)?));
-            }
-            _ => {
-                return Err(format!("Unexpected token: `{}`", source.next().unwrap()));
-            }
-        }
-    }
-
-    tokens.push(Token::Eof);
-    Ok(tokens)
-}
-
-fn make_string(source: &mut Peekable<Chars>) -> Result<String, String> {
-    source.next();
-    let mut s = String::new();
-    while let Some(&ch) = source.peek() {
-        match ch {
-            '"' => break,
-            '\\' => s.push(make_escape_sequence_char(source)?),
-            _ => s.push(source.next().unwrap()),
-        };
-    }
-    match source.peek() {
-        Some('"') => {
-            source.next();
-            Ok(s)
-        }
-        _ => Err("Syntax Error: Expected closing `\"`".to_string()),
-    }
-}
-
-fn make_char(source: &mut Peekable<Chars>) -> Result<char, String> {
-    source.next();
-    let c = match source.peek() {
-        Some(&ch) => match ch {
-            '\\' => make_escape_sequence_char(source)?,
-            '\'' => return Err("Syntax Error: Empty char literal".to_string()),
-            _ => source.next().unwrap(),
-        },
-        None => return Err("Syntax Error: Unexpected EOL".to_string()),
-    };
-    match source.peek() {
-        Some('\'') => {
-            source.next();
-            Ok(c)
-        }
-        Some(_) => Err("Syntax Error: Literal must me one character long ".to_string()),
-        None => Err("Syntax Error: Expected closing `\'`".to_string()),
-    }
-}
-
-fn make_escape_sequence_char(source: &mut Peekable<Chars>) -> Result<char, String> {
-    source.next();
-    let c = match source.peek() {
-        Some('n') => '\n',
-        Some('r') => '\r',
-        Some('t') => '\t',
-        Some('0') => '\0',
-        Some('"') => '"',
-        Some('\\') => '\\',
-        Some('\'') => '\'',
-        Some(c) => {
-            return Err(format!(
-                "Syntax Error: Unknown escape sequence character: {:?}",
-                *c
-            ))
-        }
-        None => return Err("Syntax Error: Unexpected EOL".to_string()),
-    };
-    source.next();
-    Ok(c)
-}
-
-fn make_number(source: &mut Peekable<Chars>) -> Result<f64, String> {
-    // let mut dot_count = if source.peek().unwrap() == &'.' { 1 } else { 0 };
-    let mut number = source.next().unwrap().to_string();
-    while let Some(&ch) = source.peek() {
-        match ch {
-            '0'..='9' => {
-                number.push(ch);
-                source.next();
-            }
-            // '.' => {
-            //     number.push(ch);
-            //     dot_count += 1;
-            //     if dot_count > 1 {
-            //         return Err("Syntax Error".to_string());
-            //     };
-            //     source.next();
-            // }
-            _ => break,
-        };
-    }
-
-    // Ok(if dot_count == 1 {
-    number.parse::<f64>().map_err(|err| err.to_string())
-    // } else {
-    //     Number::Int(number.parse::<i128>().map_err(|err| err.to_string())?)
-    // })
-}
-
-fn make_name(source: &mut Peekable<Chars>) -> String {
-    let mut name = source.next().unwrap().to_string();
-    while let Some(&ch) = source.peek() {
-        match ch {
-            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '$' => {
-                name.push(ch);
-                source.next();
-            }
-            _ => break,
-        }
-    }
-    name
-}
-
-fn make_operator(source: &mut Peekable<Chars>) -> Operator {
-    let ch = source.next().unwrap();
-    match ch {
-        '+' => Plus,
-        '-' => Minus,
-        '/' => Slash,
-        '%' => Percent,
-        ':' => Colon,
-        ';' => Semicolon,
-        '|' => Pipe,
-        '&' => Ampersand,
-        '^' => Caret,
-        '.' => Dot,
-        '>' => make_3char_long_operator(source, '=', '>', (Greater, GreaterEquals, GreaterGreater)),
-        '<' => make_3char_long_operator(source, '=', '<', (Less, LessEquals, LessLess)),
-        '=' => make_2char_long_operator(source, '=', (Equals, EqualsEquals)),
-        '*' => make_2char_long_operator(source, '*', (Star, StarStar)),
-        '!' => make_2char_long_operator(source, '=', (Exclamation, ExclamationEquals)),
-        '(' => Parenthesis(Parenthesis::LParen),
-        ')' => Parenthesis(Parenthesis::RParen),
-        '[' => Parenthesis(Parenthesis::LBracket),
-        ']' => Parenthesis(Parenthesis::RBracket),
-        '{' => Parenthesis(Parenthesis::LCurly),
-        '}' => Parenthesis(Parenthesis::RCurly),
-        _ => unreachable!(),
-    }
-}
-
-fn make_2char_long_operator(
-    source: &mut Peekable<Chars>,
-    c: char,
-    operators: (Operator, Operator),
-) -> Operator {
-    match source.peek() {
-        Some(&ch) => match ch {
-            ch if ch == c => {
-                source.next();
-                operators.1
-            }
-            _ => operators.0,
-        },
-        _ => operators.0,
-    }
-}
-
-fn make_3char_long_operator(
-    source: &mut Peekable<Chars>,
-    c1: char,
-    c2: char,
-    operators: (Operator, Operator, Operator),
-) -> Operator {
-    match source.peek() {
-        Some(&ch) => match ch {
-            ch if ch == c1 => {
-                source.next();
-                operators.1
-            }
-            ch if ch == c2 => {
-                source.next();
-                operators.2
-            }
-            _ => operators.0,
-        },
-        _ => operators.0,
-    }
-}
+use std::str::Chars;
+
+use crate::{
+    error::{LexError, LexErrorKind},
+    stdlib::bigint::BigInt,
+};
+
+use super::{
+    span::{Position, Span},
+    tokens::{
+        Operator::{self, *},
+        Parenthesis::{self, *},
+        Token, TokenKind,
+    },
+};
+
+/// Character stream that keeps track of the current position in the source.
+struct Cursor<'a> {
+    chars: Chars<'a>,
+    pos: Position,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(source: &'a str, start: Position) -> Self {
+        Self {
+            chars: source.chars(),
+            pos: start,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.clone().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.chars.clone().nth(n)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+        self.pos.offset += ch.len_utf8();
+        if ch == '\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else {
+            self.pos.column += 1;
+        }
+        Some(ch)
+    }
+
+    fn pos(&self) -> Position {
+        self.pos
+    }
+}
+
+/// Lexes `source` as if it started at `start`, so that spans can point into a
+/// larger text, e.g. the whole REPL session.
+pub fn lex(source: &str, start: Position) -> Result<Vec<Token>, LexError> {
+    let mut tokens = vec![];
+    let mut source = Cursor::new(source, start);
+    // open brackets, newlines inside `(` and `[` don't end statements
+    let mut nesting = vec![];
+
+    while let Some(ch) = source.peek() {
+        let start = source.pos();
+        let error = |kind, source: &Cursor| LexError::new(kind, Span::new(start, source.pos()));
+        let kind = match ch {
+            '.' if !matches!(source.peek_nth(1), Some('0'..='9')) => {
+                TokenKind::Operator(make_operator(&mut source))
+            }
+            '0'..='9' | '.' => make_number(&mut source).map_err(|kind| error(kind, &source))?,
+            '/' if source.peek_nth(1) == Some('/') => match make_line_comment(&mut source) {
+                Some(doc) => TokenKind::DocComment(doc),
+                None => continue,
+            },
+            '/' if source.peek_nth(1) == Some('*') => {
+                skip_block_comment(&mut source)?;
+                continue;
+            }
+            'a'..='z' | 'A'..='Z' | '_' | '$' => TokenKind::Ident(make_name(&mut source)),
+            '=' | '+' | '-' | '*' | '/' | '%' | '!' | '>' | '<' | ':' | ';' | '(' | ')' | '['
+            | ']' | '{' | '}' | '|' | '^' | '&' | ',' | '?' => {
+                TokenKind::Operator(make_operator(&mut source))
+            }
+            '\n' => {
+                source.next();
+                if matches!(nesting.last(), Some(LParen | LBracket)) {
+                    continue;
+                }
+                TokenKind::Newline
+            }
+            ' ' | '\r' | '\t' => {
+                source.next();
+                continue;
+            }
+            '"' => {
+                TokenKind::String(make_string(&mut source).map_err(|kind| error(kind, &source))?)
+            }
+            '\'' => TokenKind::Char(make_char(&mut source).map_err(|kind| error(kind, &source))?),
+            _ => {
+                source.next();
+                return Err(error(LexErrorKind::UnexpectedChar(ch), &source));
+            }
+        };
+        if let TokenKind::Operator(Parenthesis(paren)) = kind {
+            match paren {
+                LParen | LBracket | LCurly => nesting.push(paren),
+                RParen | RBracket | RCurly => {
+                    nesting.pop();
+                }
+            }
+        }
+        tokens.push(Token {
+            kind,
+            span: Span::new(start, source.pos()),
+        });
+    }
+
+    let end = source.pos();
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        span: Span::new(end, end),
+    });
+    Ok(tokens)
+}
+
+/// Eats a `//` comment up to the end of the line. Returns the text of `///`
+/// doc comments, plain comments (including `////...`) are dropped.
+fn make_line_comment(source: &mut Cursor) -> Option<String> {
+    source.next();
+    source.next();
+    let is_doc = source.peek() == Some('/') && source.peek_nth(1) != Some('/');
+    if is_doc {
+        source.next();
+    }
+
+    let mut text = String::new();
+    while let Some(ch) = source.peek() {
+        if ch == '\n' {
+            break;
+        }
+        text.push(ch);
+        source.next();
+    }
+
+    if is_doc {
+        Some(text.strip_suffix('\r').unwrap_or(&text).to_string())
+    } else {
+        None
+    }
+}
+
+/// Eats a `/* */` comment, block comments nest.
+fn skip_block_comment(source: &mut Cursor) -> Result<(), LexError> {
+    let start = source.pos();
+    source.next();
+    source.next();
+    let mut depth = 1;
+    while depth > 0 {
+        match (source.next(), source.peek()) {
+            (Some('/'), Some('*')) => {
+                source.next();
+                depth += 1;
+            }
+            (Some('*'), Some('/')) => {
+                source.next();
+                depth -= 1;
+            }
+            (Some(_), _) => {}
+            (None, _) => {
+                let opening = Position {
+                    offset: start.offset + 2,
+                    column: start.column + 2,
+                    ..start
+                };
+                return Err(LexError::new(
+                    LexErrorKind::UnterminatedBlockComment,
+                    Span::new(start, opening),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn make_string(source: &mut Cursor) -> Result<String, LexErrorKind> {
+    source.next();
+    let mut s = String::new();
+    while let Some(ch) = source.peek() {
+        match ch {
+            '"' => break,
+            '\\' => s.push(make_escape_sequence_char(source)?),
+            _ => s.push(source.next().unwrap()),
+        };
+    }
+    match source.peek() {
+        Some('"') => {
+            source.next();
+            Ok(s)
+        }
+        _ => Err(LexErrorKind::UnterminatedString),
+    }
+}
+
+fn make_char(source: &mut Cursor) -> Result<char, LexErrorKind> {
+    source.next();
+    let c = match source.peek() {
+        Some(ch) => match ch {
+            '\\' => make_escape_sequence_char(source)?,
+            '\'' => return Err(LexErrorKind::EmptyChar),
+            _ => source.next().unwrap(),
+        },
+        None => return Err(LexErrorKind::UnexpectedEol),
+    };
+    match source.peek() {
+        Some('\'') => {
+            source.next();
+            Ok(c)
+        }
+        Some(_) => Err(LexErrorKind::CharTooLong),
+        None => Err(LexErrorKind::UnterminatedChar),
+    }
+}
+
+fn make_escape_sequence_char(source: &mut Cursor) -> Result<char, LexErrorKind> {
+    source.next();
+    let c = match source.peek() {
+        Some('n') => '\n',
+        Some('r') => '\r',
+        Some('t') => '\t',
+        Some('0') => '\0',
+        Some('"') => '"',
+        Some('\\') => '\\',
+        Some('\'') => '\'',
+        Some(c) => return Err(LexErrorKind::UnknownEscape(c)),
+        None => return Err(LexErrorKind::UnexpectedEol),
+    };
+    source.next();
+    Ok(c)
+}
+
+fn make_number(source: &mut Cursor) -> Result<TokenKind, LexErrorKind> {
+    if source.peek() == Some('0') {
+        let radix = match source.peek_nth(1) {
+            Some('x') => Some(16),
+            Some('o') => Some(8),
+            Some('b') => Some(2),
+            _ => None,
+        };
+        if let Some(radix) = radix {
+            source.next();
+            source.next();
+            return make_radix_number(source, radix);
+        }
+    }
+
+    let mut number = String::new();
+    let mut is_int = true;
+    push_digits(source, &mut number);
+
+    if source.peek() == Some('.') {
+        match source.peek_nth(1) {
+            // fraction: `1.5`, `.5`
+            Some('0'..='9') => {
+                number.push(source.next().unwrap());
+                push_digits(source, &mut number);
+                is_int = false;
+            }
+            // `1..2` and `1.foo` keep the dot for the next token
+            Some('.') => {}
+            Some(ch) if ch.is_alphabetic() || ch == '_' || ch == '$' => {}
+            // floatpoint: `1.`
+            _ => {
+                number.push(source.next().unwrap());
+                is_int = false;
+            }
+        }
+    }
+
+    if let Some('e') | Some('E') = source.peek() {
+        number.push(source.next().unwrap());
+        is_int = false;
+        if let Some(sign @ '+') | Some(sign @ '-') = source.peek() {
+            number.push(sign);
+            source.next();
+        }
+        if !push_digits(source, &mut number) {
+            return Err(LexErrorKind::InvalidNumber(
+                "expected at least one digit in exponent".to_string(),
+            ));
+        }
+    }
+
+    if let (Some('.'), Some('0'..='9')) = (source.peek(), source.peek_nth(1)) {
+        while let Some('0'..='9') | Some('.') = source.peek() {
+            source.next();
+        }
+        return Err(LexErrorKind::InvalidNumber(
+            "too many decimal points".to_string(),
+        ));
+    }
+
+    if is_int {
+        return Ok(make_int(&number, 10));
+    }
+    number
+        .parse::<f64>()
+        .map(TokenKind::Number)
+        .map_err(|err| LexErrorKind::InvalidNumber(err.to_string()))
+}
+
+/// Consumes a run of decimal digits and `_` separators, returns whether there
+/// was any digit.
+fn push_digits(source: &mut Cursor, number: &mut String) -> bool {
+    let len = number.len();
+    if let Some('0'..='9') = source.peek() {
+        while let Some(ch @ '0'..='9') | Some(ch @ '_') = source.peek() {
+            if ch != '_' {
+                number.push(ch);
+            }
+            source.next();
+        }
+    }
+    number.len() > len
+}
+
+/// Hex, octal and binary literals, the `0x`/`0o`/`0b` prefix is already eaten.
+fn make_radix_number(source: &mut Cursor, radix: u32) -> Result<TokenKind, LexErrorKind> {
+    let mut digits = String::new();
+    let mut invalid = None;
+    while let Some(ch) = source.peek() {
+        match ch {
+            '_' => {}
+            ch if ch.is_ascii_alphanumeric() => {
+                if ch.is_digit(radix) {
+                    digits.push(ch);
+                } else {
+                    invalid.get_or_insert(ch);
+                }
+            }
+            _ => break,
+        }
+        source.next();
+    }
+
+    if let Some(ch) = invalid {
+        return Err(LexErrorKind::InvalidNumber(format!(
+            "invalid digit `{}` for a base {} literal",
+            ch, radix
+        )));
+    }
+    if digits.is_empty() {
+        return Err(LexErrorKind::InvalidNumber(format!(
+            "no digits in a base {} literal",
+            radix
+        )));
+    }
+
+    Ok(make_int(&digits, radix))
+}
+
+/// `digits` must be valid in `radix`. Literals that don't fit into `i64`
+/// become big integers.
+fn make_int(digits: &str, radix: u32) -> TokenKind {
+    match i64::from_str_radix(digits, radix) {
+        Ok(n) => TokenKind::Int(n),
+        Err(_) => TokenKind::BigInt(BigInt::from_str_radix(digits, radix).unwrap()),
+    }
+}
+
+fn make_name(source: &mut Cursor) -> String {
+    let mut name = source.next().unwrap().to_string();
+    while let Some(ch) = source.peek() {
+        match ch {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '$' => {
+                name.push(ch);
+                source.next();
+            }
+            _ => break,
+        }
+    }
+    name
+}
+
+fn make_operator(source: &mut Cursor) -> Operator {
+    let ch = source.next().unwrap();
+    let op = match ch {
+        '+' => Plus,
+        '-' => Minus,
+        '/' => Slash,
+        '%' => Percent,
+        ':' => Colon,
+        ';' => Semicolon,
+
+        '^' => Caret,
+        ',' => Comma,
+        '.' => match source.peek() {
+            Some('.') => {
+                source.next();
+                make_2char_long_operator(source, '=', (DotDot, DotDotEquals))
+            }
+            _ => Dot,
+        },
+        '>' => make_3char_long_operator(source, '=', '>', (Greater, GreaterEquals, GreaterGreater)),
+        '<' => make_3char_long_operator(source, '=', '<', (Less, LessEquals, LessLess)),
+        '=' => make_3char_long_operator(source, '=', '>', (Equals, EqualsEquals, FatArrow)),
+        '*' => make_2char_long_operator(source, '*', (Star, StarStar)),
+        '|' => make_2char_long_operator(source, '|', (Pipe, PipePipe)),
+        '&' => make_2char_long_operator(source, '&', (Ampersand, AmpersandAmpersand)),
+        // `?.5` is a `?` followed by a number
+        '?' if source.peek() == Some('.') && !matches!(source.peek_nth(1), Some('0'..='9')) => {
+            source.next();
+            QuestionDot
+        }
+        '?' => make_2char_long_operator(source, '?', (Question, QuestionQuestion)),
+        '!' => make_2char_long_operator(source, '=', (Exclamation, ExclamationEquals)),
+        '(' => Parenthesis(Parenthesis::LParen),
+        ')' => Parenthesis(Parenthesis::RParen),
+        '[' => Parenthesis(Parenthesis::LBracket),
+        ']' => Parenthesis(Parenthesis::RBracket),
+        '{' => Parenthesis(Parenthesis::LCurly),
+        '}' => Parenthesis(Parenthesis::RCurly),
+        _ => unreachable!(),
+    };
+    match op.compound() {
+        Some(compound) if source.peek() == Some('=') => {
+            source.next();
+            compound
+        }
+        _ => op,
+    }
+}
+
+fn make_2char_long_operator(
+    source: &mut Cursor,
+    c: char,
+    operators: (Operator, Operator),
+) -> Operator {
+    match source.peek() {
+        Some(ch) => match ch {
+            ch if ch == c => {
+                source.next();
+                operators.1
+            }
+            _ => operators.0,
+        },
+        _ => operators.0,
+    }
+}
+
+fn make_3char_long_operator(
+    source: &mut Cursor,
+    c1: char,
+    c2: char,
+    operators: (Operator, Operator, Operator),
+) -> Operator {
+    match source.peek() {
+        Some(ch) => match ch {
+            ch if ch == c1 => {
+                source.next();
+                operators.1
+            }
+            ch if ch == c2 => {
+                source.next();
+                operators.2
+            }
+            _ => operators.0,
+        },
+        _ => operators.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TokenKind::*;
+
+    /// Kinds of the tokens of `source`, without the final `Eof`.
+    fn kinds(source: &str) -> Result<Vec<TokenKind>, LexErrorKind> {
+        let mut tokens = lex(source, Position::default()).map_err(|err| err.kind)?;
+        tokens.pop();
+        Ok(tokens.into_iter().map(|token| token.kind).collect())
+    }
+
+    fn invalid_number(source: &str) -> bool {
+        matches!(kinds(source), Err(LexErrorKind::InvalidNumber(_)))
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(
+            kinds("1.5 .5 1. 1e-9 2E+3 1.5e2"),
+            Ok(vec![
+                Number(1.5),
+                Number(0.5),
+                Number(1.0),
+                Number(1e-9),
+                Number(2e3),
+                Number(150.0)
+            ])
+        );
+    }
+
+    #[test]
+    fn dots_after_integers_stay_operators() {
+        assert_eq!(kinds("1..2"), Ok(vec![Int(1), Operator(DotDot), Int(2)]));
+        assert_eq!(
+            kinds("1.max"),
+            Ok(vec![Int(1), Operator(Dot), Ident("max".to_string())])
+        );
+    }
+
+    #[test]
+    fn malformed_floats() {
+        assert!(invalid_number("1.2.3"));
+        assert!(invalid_number("1e"));
+        assert!(invalid_number("1e+"));
+    }
+
+    #[test]
+    fn radix_literals_and_separators() {
+        assert_eq!(
+            kinds("0xFF 0o755 0b1010 1_000_000 0xdead_BEEF 1_0.5"),
+            Ok(vec![
+                Int(255),
+                Int(0o755),
+                Int(10),
+                Int(1_000_000),
+                Int(0xdead_beef),
+                Number(10.5)
+            ])
+        );
+        assert_eq!(
+            kinds("0x8000_0000_0000_0000"),
+            Ok(vec![BigInt(
+                crate::stdlib::bigint::BigInt::from_str_radix("8000000000000000", 16).unwrap()
+            )])
+        );
+    }
+
+    #[test]
+    fn invalid_radix_digits() {
+        assert_eq!(
+            kinds("0b102"),
+            Err(LexErrorKind::InvalidNumber(
+                "invalid digit `2` for a base 2 literal".to_string()
+            ))
+        );
+        assert!(invalid_number("0o8"));
+        assert!(invalid_number("0xG"));
+        assert!(invalid_number("0x"));
+    }
+
+    #[test]
+    fn logical_operators() {
+        assert_eq!(
+            kinds("a&&b||c??d&e|f"),
+            Ok(vec![
+                Ident("a".to_string()),
+                Operator(AmpersandAmpersand),
+                Ident("b".to_string()),
+                Operator(PipePipe),
+                Ident("c".to_string()),
+                Operator(QuestionQuestion),
+                Ident("d".to_string()),
+                Operator(Ampersand),
+                Ident("e".to_string()),
+                Operator(Pipe),
+                Ident("f".to_string())
+            ])
+        );
+    }
+
+    #[test]
+    fn comments() {
+        let ident = |name: &str| Ident(name.to_string());
+        assert_eq!(
+            kinds("a // comment\nb //// not a doc"),
+            Ok(vec![ident("a"), Newline, ident("b")])
+        );
+        assert_eq!(
+            kinds("a /* x /* nested */ still\n comment */ b"),
+            Ok(vec![ident("a"), ident("b")])
+        );
+        assert_eq!(
+            kinds("/// Docs.\nx"),
+            Ok(vec![DocComment(" Docs.".to_string()), Newline, ident("x")])
+        );
+    }
+
+    #[test]
+    fn unterminated_block_comment() {
+        let err = lex("a\n  /* open /* closed */\n", Position::default()).unwrap_err();
+        assert_eq!(err.kind, LexErrorKind::UnterminatedBlockComment);
+        assert_eq!(
+            (
+                err.span.start.line,
+                err.span.start.column,
+                err.span.end.column
+            ),
+            (2, 3, 5)
+        );
+        assert_eq!(
+            err.kind.help().as_deref(),
+            Some("block comments nest, every `/*` needs its own `*/`")
+        );
+    }
+}
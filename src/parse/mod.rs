@@ -1,5 +1,6 @@
-pub mod interpreter;
-pub mod lexer;
-pub mod parser;
-pub mod tokens;
-pub use interpreter::Context;
+pub mod interpreter;
+pub mod lexer;
+pub mod parser;
+pub mod span;
+pub mod tokens;
+pub use interpreter::Context;
@@ -1,295 +1,1577 @@
-use std::{iter::Peekable, slice::Iter};
-
-use super::tokens::{Operator, Parenthesis, Token};
-
-const KEYWORDS: [&str; 5] = ["let", "inf", "NaN", "true", "false"];
-
-#[derive(Debug)]
-pub enum AstNode {
-    Number(f64),
-    Bool(bool),
-    Char(char),
-    String(String),
-    UnOp(Operator, Box<AstNode>),
-    BinOp(Box<AstNode>, Operator, Box<AstNode>),
-    VarCreate(String, Box<AstNode>),
-    VarAssign(String, Box<AstNode>),
-    VarAccess(String),
-}
-
-pub fn parse(tokens: Vec<Token>) -> Result<AstNode, String> {
-    let mut tokens: Peekable<Iter<Token>> = tokens.iter().peekable();
-    parse_createvar(&mut tokens)
-}
-
-pub fn parse_createvar(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, String> {
-    match tokens.peek() {
-        Some(Token::Ident(ident)) => match ident.as_str() {
-            "let" => {
-                tokens.next();
-                match tokens.next() {
-                    Some(Token::Ident(var_name)) => {
-                        if KEYWORDS.contains(&var_name.as_str()) {
-                            return Err(format!("Expected identifier, found `{}`", var_name));
-                        };
-                        match tokens.next() {
-                            Some(Token::Operator(Operator::Equals)) => Ok(AstNode::VarCreate(
-                                var_name.clone(),
-                                Box::new(parse_comparison(tokens)?),
-                            )),
-                            _ => Err("Expected `=`".to_string()),
-                        }
-                    }
-                    _ => Err("Expected identifier".to_string()),
-                }
-            }
-            _ => parse_comparison(tokens),
-        },
-        _ => parse_comparison(tokens),
-    }
-}
-
-pub fn parse_comparison(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, String> {
-    if let Some(Token::Operator(Operator::Exclamation)) = tokens.peek() {
-        tokens.next();
-        return Ok(AstNode::UnOp(
-            Operator::Exclamation,
-            Box::new(parse_comparison(tokens)?),
-        ));
-    }
-
-    let mut left_expr = parse_bitor(tokens)?;
-    while let Some(Token::Operator(op)) = tokens.peek() {
-        match op {
-            Operator::EqualsEquals => {
-                tokens.next();
-                left_expr =
-                    AstNode::BinOp(Box::new(left_expr), *op, Box::new(parse_bitor(tokens)?));
-            }
-            Operator::ExclamationEquals => {
-                tokens.next();
-                left_expr =
-                    AstNode::BinOp(Box::new(left_expr), *op, Box::new(parse_bitor(tokens)?));
-            }
-            Operator::Greater => {
-                tokens.next();
-                left_expr =
-                    AstNode::BinOp(Box::new(left_expr), *op, Box::new(parse_bitor(tokens)?));
-            }
-            Operator::Less => {
-                tokens.next();
-                left_expr =
-                    AstNode::BinOp(Box::new(left_expr), *op, Box::new(parse_bitor(tokens)?));
-            }
-            Operator::GreaterEquals => {
-                tokens.next();
-                left_expr =
-                    AstNode::BinOp(Box::new(left_expr), *op, Box::new(parse_bitor(tokens)?));
-            }
-            Operator::LessEquals => {
-                tokens.next();
-                left_expr =
-                    AstNode::BinOp(Box::new(left_expr), *op, Box::new(parse_bitor(tokens)?));
-            }
-            _ => break,
-        }
-    }
-    Ok(left_expr)
-}
-
-pub fn parse_bitor(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, String> {
-    let mut bitor = parse_bitxor(tokens)?;
-
-    while let Some(Token::Operator(op)) = tokens.peek() {
-        match op {
-            Operator::Pipe => {
-                tokens.next();
-                bitor = AstNode::BinOp(Box::new(bitor), *op, Box::new(parse_bitxor(tokens)?));
-            }
-            _ => break,
-        }
-    }
-    Ok(bitor)
-}
-
-pub fn parse_bitxor(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, String> {
-    let mut bitxor = parse_bitand(tokens)?;
-
-    while let Some(Token::Operator(op)) = tokens.peek() {
-        match op {
-            Operator::Caret => {
-                tokens.next();
-                bitxor = AstNode::BinOp(Box::new(bitxor), *op, Box::new(parse_bitand(tokens)?));
-            }
-            _ => break,
-        }
-    }
-    Ok(bitxor)
-}
-
-pub fn parse_bitand(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, String> {
-    let mut bitand = parse_shift(tokens)?;
-
-    while let Some(Token::Operator(op)) = tokens.peek() {
-        match op {
-            Operator::Ampersand => {
-                tokens.next();
-                bitand = AstNode::BinOp(Box::new(bitand), *op, Box::new(parse_shift(tokens)?));
-            }
-            _ => break,
-        }
-    }
-    Ok(bitand)
-}
-
-pub fn parse_shift(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, String> {
-    let mut shift = parse_expr(tokens)?;
-
-    while let Some(Token::Operator(op)) = tokens.peek() {
-        match op {
-            Operator::GreaterGreater => {
-                tokens.next();
-                shift = AstNode::BinOp(Box::new(shift), *op, Box::new(parse_expr(tokens)?));
-            }
-            Operator::LessLess => {
-                tokens.next();
-                shift = AstNode::BinOp(Box::new(shift), *op, Box::new(parse_expr(tokens)?));
-            }
-            _ => break,
-        }
-    }
-    Ok(shift)
-}
-
-pub fn parse_expr(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, String> {
-    let mut term = parse_term(tokens)?;
-
-    while let Some(Token::Operator(op)) = tokens.peek() {
-        match op {
-            Operator::Plus => {
-                tokens.next();
-                term = AstNode::BinOp(Box::new(term), *op, Box::new(parse_term(tokens)?));
-            }
-            Operator::Minus => {
-                tokens.next();
-                term = AstNode::BinOp(Box::new(term), *op, Box::new(parse_term(tokens)?));
-            }
-            _ => break,
-        }
-    }
-    Ok(term)
-}
-pub fn parse_term(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, String> {
-    let mut left_factor = parse_factor(tokens)?;
-
-    while let Some(Token::Operator(op)) = tokens.peek() {
-        match op {
-            Operator::Star => {
-                tokens.next();
-                left_factor =
-                    AstNode::BinOp(Box::new(left_factor), *op, Box::new(parse_factor(tokens)?));
-            }
-            Operator::Slash => {
-                tokens.next();
-                left_factor =
-                    AstNode::BinOp(Box::new(left_factor), *op, Box::new(parse_factor(tokens)?));
-            }
-            Operator::Percent => {
-                tokens.next();
-                left_factor =
-                    AstNode::BinOp(Box::new(left_factor), *op, Box::new(parse_factor(tokens)?));
-            }
-            _ => break,
-        }
-    }
-    Ok(left_factor)
-}
-
-pub fn parse_factor(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, String> {
-    let factor = match tokens.peek() {
-        Some(token) => match token {
-            Token::Operator(op) => match op {
-                Operator::Plus => {
-                    tokens.next();
-                    AstNode::UnOp(*op, Box::new(parse_factor(tokens)?))
-                }
-                Operator::Minus => {
-                    tokens.next();
-                    AstNode::UnOp(*op, Box::new(parse_factor(tokens)?))
-                }
-                _ => parse_power(tokens)?,
-            },
-            Token::Eof => return Err("Unexpected EOF".to_string()),
-            _ => parse_power(tokens)?,
-        },
-        None => return Err("Syntax error".to_string()),
-    };
-    Ok(factor)
-}
-
-pub fn parse_power(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, String> {
-    let mut atom = parse_atom(tokens)?;
-
-    while let Some(Token::Operator(op)) = tokens.peek() {
-        match op {
-            Operator::StarStar => {
-                tokens.next();
-                atom = AstNode::BinOp(Box::new(atom), *op, Box::new(parse_factor(tokens)?));
-            }
-            _ => break,
-        }
-    }
-    Ok(atom)
-}
-
-pub fn parse_atom(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, String> {
-    let atom = match tokens.next() {
-        Some(token) => match token {
-            Token::Number(n) => AstNode::Number(*n),
-            Token::Ident(ident) => {
-                if let Some(Token::Operator(Operator::Equals)) = tokens.peek() {
-                    tokens.next();
-                    if KEYWORDS.contains(&ident.as_str()) {
-                        return Err(format!("Expected identifier, found `{}`", ident));
-                    };
-                    return Ok(AstNode::VarAssign(
-                        ident.clone(),
-                        Box::new(parse_comparison(tokens)?),
-                    ));
-                };
-                match ident.as_str() {
-                    "inf" => AstNode::Number(f64::INFINITY),
-                    "NaN" => AstNode::Number(f64::NAN),
-                    "true" => AstNode::Bool(true),
-                    "false" => AstNode::Bool(false),
-                    _ => AstNode::VarAccess(ident.clone()),
-                }
-            }
-            Token::Operator(op) => match op {
-                Operator::Parenthesis(paren) => match paren {
-                    Parenthesis::LParen => {
-                        let expr = parse_comparison(tokens)?;
-                        let token = tokens.next();
-                        match token {
-                            Some(token) => match token {
-                                Token::Operator(Operator::Parenthesis(Parenthesis::RParen)) => expr,
-                                Token::Eof => return Err("Expected `)`, found EOF".to_string()),
-                                _ => return Err(format!("Expected `)`, found: {:?}", token)),
-                            },
-                            _ => unreachable!(),
-                        }
-                    }
-                    Parenthesis::RParen => return Err("Expected expression".to_string()),
-                    _ => return Err(format!("Unexpected token: {:?}", token)),
-                },
-                _ => return Err(format!("Syntax error, {}:{}", line!(), column!())),
-            },
-            Token::String(s) => AstNode::String(s.clone()),
-            Token::Char(c) => AstNode::Char(*c),
-            Token::Eof => return Err("Unexpected EOF".to_string()),
-        },
-        None => return Err(format!("Syntax error, {}:{}", line!(), column!())),
-    };
-
-    Ok(atom)
-}
+use std::{iter::Peekable, rc::Rc, slice::Iter};
+
+use crate::{
+    error::{ParseError, ParseErrorKind},
+    stdlib::bigint::BigInt,
+};
+
+use super::{
+    span::Span,
+    tokens::{Operator, Parenthesis, Token, TokenKind},
+};
+
+const KEYWORDS: [&str; 18] = [
+    "let", "mut", "const", "if", "else", "while", "for", "in", "break", "continue", "fn", "return",
+    "match", "inf", "NaN", "true", "false", "null",
+];
+
+/// Keywords that start a statement, a `{` followed by one opens a block.
+const STATEMENT_KEYWORDS: [&str; 10] = [
+    "let", "const", "fn", "if", "while", "for", "match", "return", "break", "continue",
+];
+
+#[derive(Debug)]
+pub struct AstNode {
+    pub kind: AstKind,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum AstKind {
+    Number(f64),
+    Int(i64),
+    BigInt(BigInt),
+    Bool(bool),
+    Char(char),
+    String(String),
+    Null,
+    UnOp(Operator, Box<AstNode>),
+    BinOp(Box<AstNode>, Operator, Box<AstNode>),
+    VarCreate(String, Declaration, Box<AstNode>),
+    /// `let` with a destructuring pattern like `let (a, b) = value`.
+    LetPattern(Box<Pattern>, Declaration, Box<AstNode>),
+    /// `name = value`, or a compound assignment like `name += value` that
+    /// holds the binary operator it applies.
+    VarAssign(String, Option<Operator>, Box<AstNode>),
+    /// `container[index] = value`, the operator is the same as in `VarAssign`.
+    IndexAssign(Box<AstNode>, Box<AstNode>, Option<Operator>, Box<AstNode>),
+    /// `(a, b) = value` or `[a, b] = value`, the targets can be variables,
+    /// indexed items or nested tuples and lists of them.
+    DestructuringAssign(Box<AstNode>, Box<AstNode>),
+    VarAccess(String),
+    /// Top-level statements, evaluated in the scope they are given.
+    Program(Vec<AstNode>),
+    /// `{ ... }`, evaluated in a new scope.
+    Block(Vec<AstNode>),
+    If(Box<AstNode>, Box<AstNode>, Option<Box<AstNode>>),
+    While(Box<AstNode>, Box<AstNode>),
+    /// `for name in iterable body`, with the span of the name.
+    For((String, Span), Box<AstNode>, Box<AstNode>),
+    Break(Option<Box<AstNode>>),
+    Continue,
+    In(Box<AstNode>, Box<AstNode>),
+    /// `&&`, `||` and `??`, the right side is only evaluated when the left
+    /// one doesn't decide the result.
+    ShortCircuit(Box<AstNode>, Operator, Box<AstNode>),
+    /// Function with an optional name, it and the parameters with their spans,
+    /// and body.
+    FnDecl(Option<(String, Span)>, Vec<(String, Span)>, Rc<AstNode>),
+    Return(Option<Box<AstNode>>),
+    Call(Box<AstNode>, Vec<AstNode>),
+    /// Receiver, method name and arguments. The flag is set for `?.`, which
+    /// yields null instead of calling the method when the receiver is null.
+    MethodCall(Box<AstNode>, String, Vec<AstNode>, bool),
+    List(Vec<AstNode>),
+    /// `{ key: value, ... }`, the entries in source order.
+    Map(Vec<(AstNode, AstNode)>),
+    Tuple(Vec<AstNode>),
+    Set(Vec<AstNode>),
+    Index(Box<AstNode>, Box<AstNode>),
+    Match(Box<AstNode>, Vec<MatchArm>),
+}
+
+/// `pattern if guard => body`.
+#[derive(Debug)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<AstNode>,
+    pub body: AstNode,
+}
+
+#[derive(Debug)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum PatternKind {
+    /// `_`, matches anything.
+    Wildcard,
+    /// Binds the value to a name.
+    Binding(String),
+    /// Number, string, char, bool or null literal, matches equal values.
+    Literal(AstNode),
+    /// `start..end` or `start..=end` of literals, matches values between them.
+    Range(AstNode, AstNode, bool),
+    /// `a | b`, matches if any alternative does.
+    Or(Vec<Pattern>),
+    /// `(a, b)`, matches tuples with as many items.
+    Tuple(Vec<Pattern>),
+    /// `[a, b]`, matches lists with as many items. With a rest pattern, as in
+    /// `[a, ..rest, b]`, the list can be longer and the rest pattern (a binding
+    /// or `_` for a bare `..`) gets the extra items. The index tells where it
+    /// was written.
+    List(Vec<Pattern>, Option<(usize, Box<Pattern>)>),
+    /// `{ name, key: pattern }`, matches maps that have the string keys.
+    Map(Vec<(String, Pattern)>),
+}
+
+/// How a variable was declared, decides whether it can be reassigned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Declaration {
+    Let,
+    LetMut,
+    Const,
+}
+
+impl AstNode {
+    pub fn new(kind: AstKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    fn unop(op: Operator, op_span: Span, node: AstNode) -> Self {
+        let span = op_span.to(node.span);
+        Self::new(AstKind::UnOp(op, Box::new(node)), span)
+    }
+
+    fn binop(left: AstNode, op: Operator, right: AstNode) -> Self {
+        let span = left.span.to(right.span);
+        Self::new(AstKind::BinOp(Box::new(left), op, Box::new(right)), span)
+    }
+}
+
+fn expected(expected: &str, token: &Token) -> ParseError {
+    ParseError::new(
+        ParseErrorKind::Expected {
+            expected: expected.to_string(),
+            found: token.kind.to_string(),
+        },
+        token.span,
+    )
+}
+
+/// Error for running out of tokens, `parse` points it at the `Eof` token.
+fn unexpected_eof() -> ParseError {
+    ParseError::new(ParseErrorKind::UnexpectedEof, Span::default())
+}
+
+fn peek_kind<'a>(tokens: &mut Peekable<Iter<'a, Token>>) -> Option<&'a TokenKind> {
+    tokens.peek().map(|&token| &token.kind)
+}
+
+pub fn parse(tokens: Vec<Token>) -> Result<AstNode, ParseError> {
+    let tokens: Vec<Token> = tokens
+        .into_iter()
+        .filter(|token| !matches!(token.kind, TokenKind::DocComment(_)))
+        .collect();
+    let eof = tokens.last().map(|token| token.span).unwrap_or_default();
+    let mut tokens: Peekable<Iter<Token>> = tokens.iter().peekable();
+    parse_program(&mut tokens).map_err(|mut err| {
+        if err.kind == ParseErrorKind::UnexpectedEof && err.span == Span::default() {
+            err.span = eof;
+        }
+        err
+    })
+}
+
+fn skip_separators(tokens: &mut Peekable<Iter<Token>>) {
+    while let Some(TokenKind::Operator(Operator::Semicolon)) | Some(TokenKind::Newline) =
+        peek_kind(tokens)
+    {
+        tokens.next();
+    }
+}
+
+pub fn parse_program(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let mut statements = vec![];
+    skip_separators(tokens);
+    loop {
+        if let Some(TokenKind::Eof) | None = peek_kind(tokens) {
+            break;
+        }
+
+        statements.push(parse_createvar(tokens)?);
+
+        match tokens.peek() {
+            Some(Token {
+                kind: TokenKind::Operator(Operator::Semicolon) | TokenKind::Newline,
+                ..
+            }) => skip_separators(tokens),
+            Some(Token {
+                kind: TokenKind::Eof,
+                ..
+            })
+            | None => break,
+            Some(token) => return Err(expected("`;` or newline", token)),
+        }
+    }
+
+    let span = match (statements.first(), statements.last()) {
+        (Some(first), Some(last)) => first.span.to(last.span),
+        _ => Span::default(),
+    };
+    Ok(AstNode::new(AstKind::Program(statements), span))
+}
+
+pub fn parse_block(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let start = match tokens.next() {
+        Some(Token {
+            kind: TokenKind::Operator(Operator::Parenthesis(Parenthesis::LCurly)),
+            span,
+        }) => *span,
+        Some(token) => return Err(expected("`{`", token)),
+        None => return Err(unexpected_eof()),
+    };
+    let mut statements = vec![];
+    skip_separators(tokens);
+    loop {
+        if let Some(Token {
+            kind: TokenKind::Operator(Operator::Parenthesis(Parenthesis::RCurly)),
+            span,
+        }) = tokens.peek().copied()
+        {
+            tokens.next();
+            return Ok(AstNode::new(AstKind::Block(statements), start.to(*span)));
+        }
+        if let Some(Token {
+            kind: TokenKind::Ident(ident),
+            span,
+        }) = tokens.peek().copied()
+        {
+            if ident == "const" {
+                return Err(ParseError::new(ParseErrorKind::NestedConst, *span));
+            }
+        }
+
+        statements.push(parse_createvar(tokens)?);
+
+        match tokens.peek().copied() {
+            Some(Token {
+                kind: TokenKind::Operator(Operator::Semicolon) | TokenKind::Newline,
+                ..
+            }) => skip_separators(tokens),
+            Some(Token {
+                kind: TokenKind::Operator(Operator::Parenthesis(Parenthesis::RCurly)),
+                ..
+            }) => {}
+            Some(token) => return Err(expected("`;`, newline or `}`", token)),
+            None => return Err(unexpected_eof()),
+        }
+    }
+}
+
+pub fn parse_if(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let start = match tokens.next() {
+        Some(token) => token.span,
+        None => return Err(unexpected_eof()),
+    };
+    let condition = parse_assignment(tokens)?;
+    let then = parse_block(tokens)?;
+    let mut span = start.to(then.span);
+
+    // `else` may start on the line after the closing `}`
+    let mut lookahead = tokens.clone();
+    while let Some(TokenKind::Newline) = peek_kind(&mut lookahead) {
+        lookahead.next();
+    }
+    let otherwise = match peek_kind(&mut lookahead) {
+        Some(TokenKind::Ident(ident)) if ident == "else" => {
+            lookahead.next();
+            *tokens = lookahead;
+            let node = match peek_kind(tokens) {
+                Some(TokenKind::Ident(ident)) if ident == "if" => parse_if(tokens)?,
+                _ => parse_block(tokens)?,
+            };
+            span = span.to(node.span);
+            Some(Box::new(node))
+        }
+        _ => None,
+    };
+
+    Ok(AstNode::new(
+        AstKind::If(Box::new(condition), Box::new(then), otherwise),
+        span,
+    ))
+}
+
+pub fn parse_while(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let start = match tokens.next() {
+        Some(token) => token.span,
+        None => return Err(unexpected_eof()),
+    };
+    let condition = parse_assignment(tokens)?;
+    let body = parse_block(tokens)?;
+    let span = start.to(body.span);
+    Ok(AstNode::new(
+        AstKind::While(Box::new(condition), Box::new(body)),
+        span,
+    ))
+}
+
+pub fn parse_for(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let start = match tokens.next() {
+        Some(token) => token.span,
+        None => return Err(unexpected_eof()),
+    };
+    let var_name = parse_spanned_ident(tokens)?;
+    match tokens.next() {
+        Some(Token {
+            kind: TokenKind::Ident(ident),
+            ..
+        }) if ident == "in" => {}
+        Some(token) => return Err(expected("`in`", token)),
+        None => return Err(unexpected_eof()),
+    }
+    let iterable = parse_assignment(tokens)?;
+    let body = parse_block(tokens)?;
+    let span = start.to(body.span);
+    Ok(AstNode::new(
+        AstKind::For(var_name, Box::new(iterable), Box::new(body)),
+        span,
+    ))
+}
+
+/// Parses `continue`, and `break` and `return` with an optional value.
+pub fn parse_jump(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let (keyword, span) = match tokens.next() {
+        Some(Token {
+            kind: TokenKind::Ident(ident),
+            span,
+        }) => (ident.as_str(), *span),
+        Some(token) => return Err(expected("`break`, `continue` or `return`", token)),
+        None => return Err(unexpected_eof()),
+    };
+    if keyword == "continue" {
+        return Ok(AstNode::new(AstKind::Continue, span));
+    }
+    let (value, span) = match peek_kind(tokens) {
+        Some(TokenKind::Operator(
+            Operator::Semicolon | Operator::Parenthesis(Parenthesis::RCurly),
+        ))
+        | Some(TokenKind::Newline)
+        | Some(TokenKind::Eof)
+        | None => (None, span),
+        _ => {
+            let value = parse_assignment(tokens)?;
+            let span = span.to(value.span);
+            (Some(Box::new(value)), span)
+        }
+    };
+    let kind = match keyword {
+        "return" => AstKind::Return(value),
+        _ => AstKind::Break(value),
+    };
+    Ok(AstNode::new(kind, span))
+}
+
+/// Parses a name that is not a keyword.
+fn parse_ident(tokens: &mut Peekable<Iter<Token>>) -> Result<String, ParseError> {
+    match tokens.next() {
+        Some(Token {
+            kind: TokenKind::Ident(ident),
+            span,
+        }) => {
+            if KEYWORDS.contains(&ident.as_str()) {
+                return Err(ParseError::new(
+                    ParseErrorKind::KeywordAsIdentifier(ident.clone()),
+                    *span,
+                ));
+            };
+            Ok(ident.clone())
+        }
+        Some(token) => Err(expected("identifier", token)),
+        None => Err(unexpected_eof()),
+    }
+}
+
+/// Parses an identifier along with where it's written.
+fn parse_spanned_ident(tokens: &mut Peekable<Iter<Token>>) -> Result<(String, Span), ParseError> {
+    let span = match tokens.peek() {
+        Some(token) => token.span,
+        None => return Err(unexpected_eof()),
+    };
+    Ok((parse_ident(tokens)?, span))
+}
+
+/// Parses `match value { pattern => body, ... }`. Arms are separated by commas
+/// or newlines.
+pub fn parse_match(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let start = match tokens.next() {
+        Some(token) => token.span,
+        None => return Err(unexpected_eof()),
+    };
+    let value = parse_assignment(tokens)?;
+    match tokens.next() {
+        Some(Token {
+            kind: TokenKind::Operator(Operator::Parenthesis(Parenthesis::LCurly)),
+            ..
+        }) => {}
+        Some(token) => return Err(expected("`{`", token)),
+        None => return Err(unexpected_eof()),
+    }
+    let mut arms = vec![];
+    loop {
+        while let Some(TokenKind::Newline) = peek_kind(tokens) {
+            tokens.next();
+        }
+        if let Some(Token {
+            kind: TokenKind::Operator(Operator::Parenthesis(Parenthesis::RCurly)),
+            span,
+        }) = tokens.peek().copied()
+        {
+            tokens.next();
+            return Ok(AstNode::new(
+                AstKind::Match(Box::new(value), arms),
+                start.to(*span),
+            ));
+        }
+
+        let pattern = parse_pattern(tokens)?;
+        let guard = match peek_kind(tokens) {
+            Some(TokenKind::Ident(ident)) if ident == "if" => {
+                tokens.next();
+                Some(parse_assignment(tokens)?)
+            }
+            _ => None,
+        };
+        match tokens.next() {
+            Some(Token {
+                kind: TokenKind::Operator(Operator::FatArrow),
+                ..
+            }) => {}
+            Some(token) => return Err(expected("`=>`", token)),
+            None => return Err(unexpected_eof()),
+        }
+        let body = parse_assignment(tokens)?;
+        arms.push(MatchArm {
+            pattern,
+            guard,
+            body,
+        });
+
+        match tokens.peek().copied() {
+            Some(Token {
+                kind: TokenKind::Operator(Operator::Comma),
+                ..
+            }) => {
+                tokens.next();
+            }
+            Some(Token {
+                kind:
+                    TokenKind::Newline | TokenKind::Operator(Operator::Parenthesis(Parenthesis::RCurly)),
+                ..
+            }) => {}
+            Some(token) => return Err(expected("`,`, newline or `}`", token)),
+            None => return Err(unexpected_eof()),
+        }
+    }
+}
+
+/// Parses a pattern with alternatives, `a | b | ...`.
+pub fn parse_pattern(tokens: &mut Peekable<Iter<Token>>) -> Result<Pattern, ParseError> {
+    let first = parse_single_pattern(tokens)?;
+    if !matches!(peek_kind(tokens), Some(TokenKind::Operator(Operator::Pipe))) {
+        return Ok(first);
+    }
+    let mut span = first.span;
+    let mut alternatives = vec![first];
+    while let Some(TokenKind::Operator(Operator::Pipe)) = peek_kind(tokens) {
+        tokens.next();
+        let alternative = parse_single_pattern(tokens)?;
+        span = span.to(alternative.span);
+        alternatives.push(alternative);
+    }
+    Ok(Pattern {
+        kind: PatternKind::Or(alternatives),
+        span,
+    })
+}
+
+fn parse_single_pattern(tokens: &mut Peekable<Iter<Token>>) -> Result<Pattern, ParseError> {
+    if let Some(Token {
+        kind: TokenKind::Operator(Operator::Parenthesis(paren)),
+        span,
+    }) = tokens.peek().copied()
+    {
+        match paren {
+            Parenthesis::LParen => return parse_tuple_pattern(tokens),
+            Parenthesis::LBracket => return parse_list_pattern(tokens),
+            Parenthesis::LCurly => return parse_map_pattern(tokens),
+            _ => return Err(ParseError::new(ParseErrorKind::InvalidPattern, *span)),
+        }
+    }
+    if let Some(Token {
+        kind: TokenKind::Ident(ident),
+        span,
+    }) = tokens.peek().copied()
+    {
+        let kind = match ident.as_str() {
+            "_" => {
+                tokens.next();
+                PatternKind::Wildcard
+            }
+            "true" | "false" | "null" | "inf" | "NaN" => PatternKind::Literal(parse_atom(tokens)?),
+            _ => PatternKind::Binding(parse_ident(tokens)?),
+        };
+        return Ok(Pattern { kind, span: *span });
+    }
+
+    let start = parse_pattern_literal(tokens)?;
+    match peek_kind(tokens) {
+        Some(TokenKind::Operator(op @ (Operator::DotDot | Operator::DotDotEquals))) => {
+            let inclusive = *op == Operator::DotDotEquals;
+            tokens.next();
+            let end = parse_pattern_literal(tokens)?;
+            Ok(Pattern {
+                span: start.span.to(end.span),
+                kind: PatternKind::Range(start, end, inclusive),
+            })
+        }
+        _ => Ok(Pattern {
+            span: start.span,
+            kind: PatternKind::Literal(start),
+        }),
+    }
+}
+
+/// Parses `(a, b)`, `(a,)` or `()`. A single pattern without a comma is just
+/// parenthesized.
+fn parse_tuple_pattern(tokens: &mut Peekable<Iter<Token>>) -> Result<Pattern, ParseError> {
+    let start = match tokens.next() {
+        Some(token) => token.span,
+        None => return Err(unexpected_eof()),
+    };
+    let close = Operator::Parenthesis(Parenthesis::RParen);
+    let (items, end) = match tokens.peek().copied() {
+        Some(Token {
+            kind: TokenKind::Operator(op),
+            ..
+        }) if *op == close => parse_delimited(tokens, close, parse_pattern)?,
+        _ => {
+            let mut first = parse_pattern(tokens)?;
+            match tokens.next() {
+                Some(Token {
+                    kind: TokenKind::Operator(Operator::Comma),
+                    ..
+                }) => {
+                    let (rest, end) = parse_delimited(tokens, close, parse_pattern)?;
+                    (std::iter::once(first).chain(rest).collect(), end)
+                }
+                Some(Token {
+                    kind: TokenKind::Operator(op),
+                    span,
+                }) if *op == close => {
+                    first.span = start.to(*span);
+                    return Ok(first);
+                }
+                Some(token) => return Err(expected("`,` or `)`", token)),
+                None => return Err(unexpected_eof()),
+            }
+        }
+    };
+    Ok(Pattern {
+        kind: PatternKind::Tuple(items),
+        span: start.to(end),
+    })
+}
+
+/// Parses `[a, b]`, where one of the items can be `..name` or `..`.
+fn parse_list_pattern(tokens: &mut Peekable<Iter<Token>>) -> Result<Pattern, ParseError> {
+    let start = match tokens.next() {
+        Some(token) => token.span,
+        None => return Err(unexpected_eof()),
+    };
+    let (items, end) = parse_delimited(
+        tokens,
+        Operator::Parenthesis(Parenthesis::RBracket),
+        |tokens| match tokens.peek().copied() {
+            Some(Token {
+                kind: TokenKind::Operator(Operator::DotDot),
+                span,
+            }) => {
+                tokens.next();
+                let rest = match peek_kind(tokens) {
+                    Some(TokenKind::Ident(_)) => parse_single_pattern(tokens)?,
+                    _ => Pattern {
+                        kind: PatternKind::Wildcard,
+                        span: *span,
+                    },
+                };
+                Ok((true, rest))
+            }
+            _ => Ok((false, parse_pattern(tokens)?)),
+        },
+    )?;
+
+    let mut patterns = vec![];
+    let mut rest = None;
+    for (is_rest, pattern) in items {
+        if !is_rest {
+            patterns.push(pattern);
+        } else if rest.is_none() {
+            rest = Some((patterns.len(), Box::new(pattern)));
+        } else {
+            return Err(ParseError::new(
+                ParseErrorKind::InvalidPattern,
+                pattern.span,
+            ));
+        }
+    }
+    Ok(Pattern {
+        kind: PatternKind::List(patterns, rest),
+        span: start.to(end),
+    })
+}
+
+/// Parses `{ name, key: pattern, "some key": pattern }`, a name alone binds
+/// the value of the key with the same name.
+fn parse_map_pattern(tokens: &mut Peekable<Iter<Token>>) -> Result<Pattern, ParseError> {
+    let start = match tokens.next() {
+        Some(token) => token.span,
+        None => return Err(unexpected_eof()),
+    };
+    let (entries, end) = parse_delimited(
+        tokens,
+        Operator::Parenthesis(Parenthesis::RCurly),
+        |tokens| {
+            let (key, span) = match tokens.peek().copied() {
+                Some(Token {
+                    kind: TokenKind::String(key),
+                    span,
+                }) => {
+                    tokens.next();
+                    (key.clone(), *span)
+                }
+                Some(Token { span, .. }) => (parse_ident(tokens)?, *span),
+                None => return Err(unexpected_eof()),
+            };
+            match peek_kind(tokens) {
+                Some(TokenKind::Operator(Operator::Colon)) => {
+                    tokens.next();
+                    Ok((key, parse_pattern(tokens)?))
+                }
+                _ => {
+                    let binding = Pattern {
+                        kind: PatternKind::Binding(key.clone()),
+                        span,
+                    };
+                    Ok((key, binding))
+                }
+            }
+        },
+    )?;
+    Ok(Pattern {
+        kind: PatternKind::Map(entries),
+        span: start.to(end),
+    })
+}
+
+/// Parses a number, optionally negative, a string or a char.
+fn parse_pattern_literal(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    match tokens.peek().copied() {
+        Some(Token {
+            kind: TokenKind::Operator(Operator::Minus),
+            span,
+        }) => {
+            tokens.next();
+            let number = parse_pattern_literal(tokens)?;
+            match number.kind {
+                AstKind::Number(_) | AstKind::Int(_) | AstKind::BigInt(_) => {
+                    Ok(AstNode::unop(Operator::Minus, *span, number))
+                }
+                _ => Err(ParseError::new(ParseErrorKind::InvalidPattern, number.span)),
+            }
+        }
+        Some(Token {
+            kind:
+                TokenKind::Number(_)
+                | TokenKind::Int(_)
+                | TokenKind::BigInt(_)
+                | TokenKind::String(_)
+                | TokenKind::Char(_),
+            ..
+        }) => parse_atom(tokens),
+        Some(Token {
+            kind: TokenKind::Ident(ident),
+            ..
+        }) if ident == "inf" => parse_atom(tokens),
+        Some(token) => Err(ParseError::new(ParseErrorKind::InvalidPattern, token.span)),
+        None => Err(unexpected_eof()),
+    }
+}
+
+/// Parses `fn name(params) { ... }`, or `fn(params) { ... }` for an anonymous
+/// function.
+pub fn parse_fn(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let start = match tokens.next() {
+        Some(token) => token.span,
+        None => return Err(unexpected_eof()),
+    };
+    let name = match peek_kind(tokens) {
+        Some(TokenKind::Operator(Operator::Parenthesis(Parenthesis::LParen))) => None,
+        _ => Some(parse_spanned_ident(tokens)?),
+    };
+    match tokens.next() {
+        Some(Token {
+            kind: TokenKind::Operator(Operator::Parenthesis(Parenthesis::LParen)),
+            ..
+        }) => {}
+        Some(token) => return Err(expected("`(`", token)),
+        None => return Err(unexpected_eof()),
+    }
+    let params = parse_params(tokens, Operator::Parenthesis(Parenthesis::RParen))?;
+    let body = parse_block(tokens)?;
+    let span = start.to(body.span);
+    Ok(AstNode::new(
+        AstKind::FnDecl(name, params, Rc::new(body)),
+        span,
+    ))
+}
+
+/// Parses `|params| body`, where the body is a single expression or a block.
+/// `||` is a lambda without parameters.
+pub fn parse_lambda(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let (start, params) = match tokens.next() {
+        Some(Token {
+            kind: TokenKind::Operator(Operator::PipePipe),
+            span,
+        }) => (*span, vec![]),
+        Some(token) => (token.span, parse_params(tokens, Operator::Pipe)?),
+        None => return Err(unexpected_eof()),
+    };
+    let body = match peek_kind(tokens) {
+        Some(TokenKind::Operator(Operator::Parenthesis(Parenthesis::LCurly))) => {
+            parse_block(tokens)?
+        }
+        _ => parse_assignment(tokens)?,
+    };
+    let span = start.to(body.span);
+    Ok(AstNode::new(
+        AstKind::FnDecl(None, params, Rc::new(body)),
+        span,
+    ))
+}
+
+/// Parses comma separated parameter names up to and including `close`, each
+/// name can only be used once.
+fn parse_params(
+    tokens: &mut Peekable<Iter<Token>>,
+    close: Operator,
+) -> Result<Vec<(String, Span)>, ParseError> {
+    let (params, _) = parse_delimited(tokens, close, parse_spanned_ident)?;
+    for (i, (name, span)) in params.iter().enumerate() {
+        if params[..i].iter().any(|(other, _)| other == name) {
+            return Err(ParseError::new(
+                ParseErrorKind::DuplicateParam(name.clone()),
+                *span,
+            ));
+        }
+    }
+    Ok(params)
+}
+
+/// Parses comma separated items up to and including `close`, a trailing comma
+/// is allowed. Returns the items and the span of `close`. Newlines between the
+/// items are ignored.
+fn parse_delimited<T>(
+    tokens: &mut Peekable<Iter<Token>>,
+    close: Operator,
+    mut parse_item: impl FnMut(&mut Peekable<Iter<Token>>) -> Result<T, ParseError>,
+) -> Result<(Vec<T>, Span), ParseError> {
+    let is_close = |kind: &TokenKind| matches!(kind, TokenKind::Operator(op) if *op == close);
+    let skip_newlines = |tokens: &mut Peekable<Iter<Token>>| {
+        while let Some(TokenKind::Newline) = peek_kind(tokens) {
+            tokens.next();
+        }
+    };
+    let mut items = vec![];
+    loop {
+        skip_newlines(tokens);
+        match tokens.peek().copied() {
+            Some(token) if is_close(&token.kind) => {
+                tokens.next();
+                return Ok((items, token.span));
+            }
+            _ => {}
+        }
+        items.push(parse_item(tokens)?);
+        skip_newlines(tokens);
+        match tokens.peek().copied() {
+            Some(Token {
+                kind: TokenKind::Operator(Operator::Comma),
+                ..
+            }) => {
+                tokens.next();
+            }
+            Some(token) if is_close(&token.kind) => {}
+            Some(token) => return Err(expected(&format!("`,` or `{}`", close), token)),
+            None => return Err(unexpected_eof()),
+        }
+    }
+}
+
+pub fn parse_createvar(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let (declaration, start) = match tokens.peek().copied() {
+        Some(Token {
+            kind: TokenKind::Ident(ident),
+            span,
+        }) => match ident.as_str() {
+            "let" => {
+                tokens.next();
+                match peek_kind(tokens) {
+                    Some(TokenKind::Ident(ident)) if ident == "mut" => {
+                        tokens.next();
+                        (Declaration::LetMut, *span)
+                    }
+                    _ => (Declaration::Let, *span),
+                }
+            }
+            "const" => {
+                tokens.next();
+                (Declaration::Const, *span)
+            }
+            _ => return parse_assignment(tokens),
+        },
+        _ => return parse_assignment(tokens),
+    };
+    // anything but a plain name is a pattern to destructure the value with
+    let target = match tokens.peek().copied() {
+        Some(Token {
+            kind: TokenKind::Ident(_),
+            span,
+        }) => Pattern {
+            kind: PatternKind::Binding(parse_ident(tokens)?),
+            span: *span,
+        },
+        _ => parse_pattern(tokens)?,
+    };
+    match tokens.next() {
+        Some(Token {
+            kind: TokenKind::Operator(Operator::Equals),
+            ..
+        }) => {}
+        Some(token) => return Err(expected("`=`", token)),
+        None => return Err(unexpected_eof()),
+    }
+    let value = parse_assignment(tokens)?;
+    let span = start.to(value.span);
+    let value = Box::new(value);
+    let kind = match target {
+        Pattern {
+            kind: PatternKind::Binding(name),
+            ..
+        } => AstKind::VarCreate(name, declaration, value),
+        pattern => AstKind::LetPattern(Box::new(pattern), declaration, value),
+    };
+    Ok(AstNode::new(kind, span))
+}
+
+/// Parses `target = value` or a compound assignment like `target += value`,
+/// where the target is a variable or an index expression, or just an
+/// expression.
+pub fn parse_assignment(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let target = parse_ternary(tokens)?;
+    let op = match peek_kind(tokens) {
+        Some(TokenKind::Operator(Operator::Equals)) => None,
+        Some(TokenKind::Operator(op)) if op.binary().is_some() => op.binary(),
+        _ => return Ok(target),
+    };
+    tokens.next();
+    let value = Box::new(parse_assignment(tokens)?);
+    let span = target.span.to(value.span);
+    let kind = match target.kind {
+        AstKind::VarAccess(name) if KEYWORDS.contains(&name.as_str()) => {
+            return Err(ParseError::new(
+                ParseErrorKind::KeywordAsIdentifier(name),
+                target.span,
+            ))
+        }
+        AstKind::VarAccess(name) => AstKind::VarAssign(name, op, value),
+        AstKind::Index(container, index) => AstKind::IndexAssign(container, index, op, value),
+        AstKind::Tuple(_) | AstKind::List(_) if op.is_none() => {
+            check_destructuring_target(&target)?;
+            AstKind::DestructuringAssign(Box::new(target), value)
+        }
+        _ => {
+            return Err(ParseError::new(
+                ParseErrorKind::InvalidAssignmentTarget,
+                target.span,
+            ))
+        }
+    };
+    Ok(AstNode::new(kind, span))
+}
+
+/// Parses `condition ? then : otherwise`, which is an `if` with an `else`.
+pub fn parse_ternary(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let condition = parse_coalesce(tokens)?;
+    if let Some(TokenKind::Operator(Operator::Question)) = peek_kind(tokens) {
+        tokens.next();
+        let then = parse_assignment(tokens)?;
+        match tokens.next() {
+            Some(Token {
+                kind: TokenKind::Operator(Operator::Colon),
+                ..
+            }) => {}
+            Some(token) => return Err(expected("`:`", token)),
+            None => return Err(unexpected_eof()),
+        }
+        let otherwise = parse_ternary(tokens)?;
+        let span = condition.span.to(otherwise.span);
+        return Ok(AstNode::new(
+            AstKind::If(
+                Box::new(condition),
+                Box::new(then),
+                Some(Box::new(otherwise)),
+            ),
+            span,
+        ));
+    }
+    Ok(condition)
+}
+
+/// Makes sure every item of a tuple or list assignment target can be assigned
+/// to.
+fn check_destructuring_target(target: &AstNode) -> Result<(), ParseError> {
+    match &target.kind {
+        AstKind::VarAccess(name) if KEYWORDS.contains(&name.as_str()) => Err(ParseError::new(
+            ParseErrorKind::KeywordAsIdentifier(name.clone()),
+            target.span,
+        )),
+        AstKind::VarAccess(_) | AstKind::Index(..) => Ok(()),
+        AstKind::Tuple(items) | AstKind::List(items) => {
+            items.iter().try_for_each(check_destructuring_target)
+        }
+        _ => Err(ParseError::new(
+            ParseErrorKind::InvalidAssignmentTarget,
+            target.span,
+        )),
+    }
+}
+
+pub fn parse_coalesce(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    parse_short_circuit(tokens, Operator::QuestionQuestion, parse_or)
+}
+
+pub fn parse_or(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    parse_short_circuit(tokens, Operator::PipePipe, parse_and)
+}
+
+pub fn parse_and(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    parse_short_circuit(tokens, Operator::AmpersandAmpersand, parse_comparison)
+}
+
+/// Parses a left-associative chain of the short-circuiting operator `op`.
+fn parse_short_circuit(
+    tokens: &mut Peekable<Iter<Token>>,
+    op: Operator,
+    parse_operand: fn(&mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError>,
+) -> Result<AstNode, ParseError> {
+    let mut left = parse_operand(tokens)?;
+    while let Some(TokenKind::Operator(next)) = peek_kind(tokens) {
+        if *next != op {
+            break;
+        }
+        tokens.next();
+        let right = parse_operand(tokens)?;
+        let span = left.span.to(right.span);
+        left = AstNode::new(
+            AstKind::ShortCircuit(Box::new(left), op, Box::new(right)),
+            span,
+        );
+    }
+    Ok(left)
+}
+
+pub fn parse_comparison(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    if let Some(Token {
+        kind: TokenKind::Operator(Operator::Exclamation),
+        span,
+    }) = tokens.peek()
+    {
+        tokens.next();
+        return Ok(AstNode::unop(
+            Operator::Exclamation,
+            *span,
+            parse_comparison(tokens)?,
+        ));
+    }
+
+    let mut left_expr = parse_range(tokens)?;
+    loop {
+        match peek_kind(tokens) {
+            Some(TokenKind::Operator(
+                op @ (Operator::EqualsEquals
+                | Operator::ExclamationEquals
+                | Operator::Greater
+                | Operator::Less
+                | Operator::GreaterEquals
+                | Operator::LessEquals),
+            )) => {
+                tokens.next();
+                left_expr = AstNode::binop(left_expr, *op, parse_range(tokens)?);
+            }
+            Some(TokenKind::Ident(ident)) if ident == "in" => {
+                tokens.next();
+                let right_expr = parse_range(tokens)?;
+                let span = left_expr.span.to(right_expr.span);
+                left_expr =
+                    AstNode::new(AstKind::In(Box::new(left_expr), Box::new(right_expr)), span);
+            }
+            _ => break,
+        }
+    }
+    Ok(left_expr)
+}
+
+pub fn parse_range(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let start = parse_bitor(tokens)?;
+    match peek_kind(tokens) {
+        Some(TokenKind::Operator(op @ (Operator::DotDot | Operator::DotDotEquals))) => {
+            tokens.next();
+            Ok(AstNode::binop(start, *op, parse_bitor(tokens)?))
+        }
+        _ => Ok(start),
+    }
+}
+
+pub fn parse_bitor(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let mut bitor = parse_bitxor(tokens)?;
+
+    while let Some(TokenKind::Operator(op)) = peek_kind(tokens) {
+        match op {
+            Operator::Pipe => {
+                tokens.next();
+                bitor = AstNode::binop(bitor, *op, parse_bitxor(tokens)?);
+            }
+            _ => break,
+        }
+    }
+    Ok(bitor)
+}
+
+pub fn parse_bitxor(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let mut bitxor = parse_bitand(tokens)?;
+
+    while let Some(TokenKind::Operator(op)) = peek_kind(tokens) {
+        match op {
+            Operator::Caret => {
+                tokens.next();
+                bitxor = AstNode::binop(bitxor, *op, parse_bitand(tokens)?);
+            }
+            _ => break,
+        }
+    }
+    Ok(bitxor)
+}
+
+pub fn parse_bitand(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let mut bitand = parse_shift(tokens)?;
+
+    while let Some(TokenKind::Operator(op)) = peek_kind(tokens) {
+        match op {
+            Operator::Ampersand => {
+                tokens.next();
+                bitand = AstNode::binop(bitand, *op, parse_shift(tokens)?);
+            }
+            _ => break,
+        }
+    }
+    Ok(bitand)
+}
+
+pub fn parse_shift(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let mut shift = parse_expr(tokens)?;
+
+    while let Some(TokenKind::Operator(op)) = peek_kind(tokens) {
+        match op {
+            Operator::GreaterGreater => {
+                tokens.next();
+                shift = AstNode::binop(shift, *op, parse_expr(tokens)?);
+            }
+            Operator::LessLess => {
+                tokens.next();
+                shift = AstNode::binop(shift, *op, parse_expr(tokens)?);
+            }
+            _ => break,
+        }
+    }
+    Ok(shift)
+}
+
+pub fn parse_expr(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let mut term = parse_term(tokens)?;
+
+    while let Some(TokenKind::Operator(op)) = peek_kind(tokens) {
+        match op {
+            Operator::Plus => {
+                tokens.next();
+                term = AstNode::binop(term, *op, parse_term(tokens)?);
+            }
+            Operator::Minus => {
+                tokens.next();
+                term = AstNode::binop(term, *op, parse_term(tokens)?);
+            }
+            _ => break,
+        }
+    }
+    Ok(term)
+}
+pub fn parse_term(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let mut left_factor = parse_factor(tokens)?;
+
+    while let Some(TokenKind::Operator(op)) = peek_kind(tokens) {
+        match op {
+            Operator::Star => {
+                tokens.next();
+                left_factor = AstNode::binop(left_factor, *op, parse_factor(tokens)?);
+            }
+            Operator::Slash => {
+                tokens.next();
+                left_factor = AstNode::binop(left_factor, *op, parse_factor(tokens)?);
+            }
+            Operator::Percent => {
+                tokens.next();
+                left_factor = AstNode::binop(left_factor, *op, parse_factor(tokens)?);
+            }
+            _ => break,
+        }
+    }
+    Ok(left_factor)
+}
+
+pub fn parse_factor(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let factor = match tokens.peek().copied() {
+        Some(token) => match &token.kind {
+            TokenKind::Operator(op) => match op {
+                Operator::Plus => {
+                    tokens.next();
+                    AstNode::unop(*op, token.span, parse_factor(tokens)?)
+                }
+                Operator::Minus => {
+                    tokens.next();
+                    AstNode::unop(*op, token.span, parse_factor(tokens)?)
+                }
+                _ => parse_power(tokens)?,
+            },
+            TokenKind::Eof => {
+                return Err(ParseError::new(ParseErrorKind::UnexpectedEof, token.span))
+            }
+            _ => parse_power(tokens)?,
+        },
+        None => return Err(unexpected_eof()),
+    };
+    Ok(factor)
+}
+
+pub fn parse_power(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let mut atom = parse_call(tokens)?;
+
+    while let Some(TokenKind::Operator(op)) = peek_kind(tokens) {
+        match op {
+            Operator::StarStar => {
+                tokens.next();
+                atom = AstNode::binop(atom, *op, parse_factor(tokens)?);
+            }
+            _ => break,
+        }
+    }
+    Ok(atom)
+}
+
+pub fn parse_call(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let mut atom = parse_atom(tokens)?;
+
+    loop {
+        match peek_kind(tokens) {
+            Some(TokenKind::Operator(op @ (Operator::Dot | Operator::QuestionDot))) => {
+                let optional = *op == Operator::QuestionDot;
+                tokens.next();
+                let name = match tokens.next() {
+                    Some(Token {
+                        kind: TokenKind::Ident(name),
+                        ..
+                    }) => name.clone(),
+                    Some(token) => return Err(expected("method name", token)),
+                    None => return Err(unexpected_eof()),
+                };
+                let (args, end) = parse_arguments(tokens)?;
+                let span = atom.span.to(end);
+                atom = AstNode::new(
+                    AstKind::MethodCall(Box::new(atom), name, args, optional),
+                    span,
+                );
+            }
+            Some(TokenKind::Operator(Operator::Parenthesis(Parenthesis::LParen))) => {
+                let (args, end) = parse_arguments(tokens)?;
+                let span = atom.span.to(end);
+                atom = AstNode::new(AstKind::Call(Box::new(atom), args), span);
+            }
+            Some(TokenKind::Operator(Operator::Parenthesis(Parenthesis::LBracket))) => {
+                tokens.next();
+                let index = parse_assignment(tokens)?;
+                let end = match tokens.next() {
+                    Some(Token {
+                        kind: TokenKind::Operator(Operator::Parenthesis(Parenthesis::RBracket)),
+                        span,
+                    }) => *span,
+                    Some(token) => return Err(expected("`]`", token)),
+                    None => return Err(unexpected_eof()),
+                };
+                let span = atom.span.to(end);
+                atom = AstNode::new(AstKind::Index(Box::new(atom), Box::new(index)), span);
+            }
+            _ => break,
+        }
+    }
+    Ok(atom)
+}
+
+/// Parses a parenthesized, comma separated argument list. Returns the
+/// arguments and the span of the closing parenthesis.
+pub fn parse_arguments(
+    tokens: &mut Peekable<Iter<Token>>,
+) -> Result<(Vec<AstNode>, Span), ParseError> {
+    match tokens.next() {
+        Some(Token {
+            kind: TokenKind::Operator(Operator::Parenthesis(Parenthesis::LParen)),
+            ..
+        }) => {}
+        Some(token) => return Err(expected("`(`", token)),
+        None => return Err(unexpected_eof()),
+    }
+    parse_delimited(
+        tokens,
+        Operator::Parenthesis(Parenthesis::RParen),
+        parse_assignment,
+    )
+}
+
+/// What a `{` starts.
+enum Braces {
+    Block,
+    Map,
+    Set,
+}
+
+/// Looks past the `{` to tell blocks, maps and sets apart. `{}` is an empty
+/// map and `{,}` an empty set. Otherwise the first `:` or `,` outside of
+/// nested brackets and lambda parameters, before the first statement ends,
+/// makes it a map or a set. A statement keyword always starts a block.
+fn braces(tokens: &mut Peekable<Iter<Token>>) -> Braces {
+    let mut ahead = tokens.clone().skip(1).map(|token| &token.kind).peekable();
+    while let Some(TokenKind::Newline) = ahead.peek() {
+        ahead.next();
+    }
+    match ahead.peek() {
+        Some(TokenKind::Operator(Operator::Parenthesis(Parenthesis::RCurly))) => {
+            return Braces::Map
+        }
+        Some(TokenKind::Operator(Operator::Comma)) => return Braces::Set,
+        Some(TokenKind::Ident(ident)) if STATEMENT_KEYWORDS.contains(&ident.as_str()) => {
+            return Braces::Block
+        }
+        _ => {}
+    }
+    let mut depth = 0;
+    // each `?` of a conditional expression is followed by its own `:`
+    let mut conditionals = 0;
+    // a lambda's parameters are separated by commas too
+    let mut lambda_params = false;
+    let mut previous = None;
+    for kind in ahead {
+        let before = previous.replace(kind);
+        match kind {
+            TokenKind::Operator(Operator::Pipe) if lambda_params => lambda_params = false,
+            TokenKind::Eof => break,
+            _ if lambda_params => {}
+            TokenKind::Operator(Operator::Pipe) if !before.is_some_and(ends_operand) => {
+                lambda_params = true
+            }
+            TokenKind::Operator(Operator::Parenthesis(
+                Parenthesis::LParen | Parenthesis::LBracket | Parenthesis::LCurly,
+            )) => depth += 1,
+            TokenKind::Operator(Operator::Parenthesis(
+                Parenthesis::RParen | Parenthesis::RBracket | Parenthesis::RCurly,
+            )) if depth > 0 => depth -= 1,
+            TokenKind::Operator(Operator::Question) if depth == 0 => conditionals += 1,
+            TokenKind::Operator(Operator::Colon) if depth == 0 && conditionals > 0 => {
+                conditionals -= 1
+            }
+            TokenKind::Operator(Operator::Colon) if depth == 0 => return Braces::Map,
+            TokenKind::Operator(Operator::Comma) if depth == 0 => return Braces::Set,
+            TokenKind::Operator(Operator::Parenthesis(Parenthesis::RCurly))
+            | TokenKind::Operator(Operator::Semicolon)
+            | TokenKind::Newline
+                if depth == 0 =>
+            {
+                break
+            }
+            _ => {}
+        }
+    }
+    Braces::Block
+}
+
+/// Whether an operand can end with `kind`, which makes a following `|` the
+/// binary operator rather than the start of a lambda.
+fn ends_operand(kind: &TokenKind) -> bool {
+    match kind {
+        TokenKind::Number(_)
+        | TokenKind::Int(_)
+        | TokenKind::BigInt(_)
+        | TokenKind::Char(_)
+        | TokenKind::String(_) => true,
+        TokenKind::Ident(ident) => {
+            !KEYWORDS.contains(&ident.as_str())
+                || matches!(ident.as_str(), "inf" | "NaN" | "true" | "false" | "null")
+        }
+        TokenKind::Operator(Operator::Parenthesis(
+            Parenthesis::RParen | Parenthesis::RBracket | Parenthesis::RCurly,
+        )) => true,
+        _ => false,
+    }
+}
+
+/// Parses `{ key: value, ... }`.
+pub fn parse_map(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let start = match tokens.next() {
+        Some(token) => token.span,
+        None => return Err(unexpected_eof()),
+    };
+    let (entries, end) = parse_delimited(
+        tokens,
+        Operator::Parenthesis(Parenthesis::RCurly),
+        |tokens| {
+            let key = parse_assignment(tokens)?;
+            match tokens.next() {
+                Some(Token {
+                    kind: TokenKind::Operator(Operator::Colon),
+                    ..
+                }) => {}
+                Some(token) => return Err(expected("`:`", token)),
+                None => return Err(unexpected_eof()),
+            }
+            Ok((key, parse_assignment(tokens)?))
+        },
+    )?;
+    Ok(AstNode::new(AstKind::Map(entries), start.to(end)))
+}
+
+/// Parses `{ item, ... }`, or `{,}` for an empty set.
+pub fn parse_set(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    let start = match tokens.next() {
+        Some(token) => token.span,
+        None => return Err(unexpected_eof()),
+    };
+    let mut ahead = tokens.clone();
+    if let (
+        Some(TokenKind::Operator(Operator::Comma)),
+        Some(TokenKind::Operator(Operator::Parenthesis(Parenthesis::RCurly))),
+    ) = (
+        ahead.next().map(|token| &token.kind),
+        ahead.next().map(|token| &token.kind),
+    ) {
+        tokens.next();
+    }
+    let (items, end) = parse_delimited(
+        tokens,
+        Operator::Parenthesis(Parenthesis::RCurly),
+        parse_assignment,
+    )?;
+    Ok(AstNode::new(AstKind::Set(items), start.to(end)))
+}
+
+/// Parses what follows a `(`: a parenthesized expression, or a tuple if
+/// there's a comma, as in `()`, `(x,)` and `(x, y)`.
+fn parse_parens(tokens: &mut Peekable<Iter<Token>>, start: Span) -> Result<AstNode, ParseError> {
+    let close = Operator::Parenthesis(Parenthesis::RParen);
+    if let Some(Token {
+        kind: TokenKind::Operator(op),
+        span,
+    }) = tokens.peek().copied()
+    {
+        if *op == close {
+            tokens.next();
+            return Ok(AstNode::new(AstKind::Tuple(vec![]), start.to(*span)));
+        }
+    }
+    let mut expr = parse_assignment(tokens)?;
+    match tokens.next() {
+        Some(Token {
+            kind: TokenKind::Operator(Operator::Comma),
+            ..
+        }) => {
+            let (rest, end) = parse_delimited(tokens, close, parse_assignment)?;
+            let items = std::iter::once(expr).chain(rest).collect();
+            Ok(AstNode::new(AstKind::Tuple(items), start.to(end)))
+        }
+        Some(Token {
+            kind: TokenKind::Operator(op),
+            span,
+        }) if *op == close => {
+            expr.span = start.to(*span);
+            Ok(expr)
+        }
+        Some(token) => Err(expected("`,` or `)`", token)),
+        None => Err(unexpected_eof()),
+    }
+}
+
+pub fn parse_atom(tokens: &mut Peekable<Iter<Token>>) -> Result<AstNode, ParseError> {
+    match peek_kind(tokens) {
+        Some(TokenKind::Operator(Operator::Pipe | Operator::PipePipe)) => {
+            return parse_lambda(tokens)
+        }
+        Some(TokenKind::Operator(Operator::Parenthesis(Parenthesis::LCurly))) => {
+            return match braces(tokens) {
+                Braces::Block => parse_block(tokens),
+                Braces::Map => parse_map(tokens),
+                Braces::Set => parse_set(tokens),
+            };
+        }
+        _ => {}
+    }
+    if let Some(TokenKind::Ident(ident)) = peek_kind(tokens) {
+        match ident.as_str() {
+            "if" => return parse_if(tokens),
+            "while" => return parse_while(tokens),
+            "for" => return parse_for(tokens),
+            "fn" => return parse_fn(tokens),
+            "match" => return parse_match(tokens),
+            "break" | "continue" | "return" => return parse_jump(tokens),
+            _ => {}
+        }
+    }
+    let token = match tokens.next() {
+        Some(token) => token,
+        None => return Err(unexpected_eof()),
+    };
+    let span = token.span;
+    let atom = match &token.kind {
+        TokenKind::Number(n) => AstNode::new(AstKind::Number(*n), span),
+        TokenKind::Int(n) => AstNode::new(AstKind::Int(*n), span),
+        TokenKind::BigInt(n) => AstNode::new(AstKind::BigInt(n.clone()), span),
+        TokenKind::Ident(ident) => {
+            let kind = match ident.as_str() {
+                "inf" => AstKind::Number(f64::INFINITY),
+                "NaN" => AstKind::Number(f64::NAN),
+                "true" => AstKind::Bool(true),
+                "false" => AstKind::Bool(false),
+                "null" => AstKind::Null,
+                _ => AstKind::VarAccess(ident.clone()),
+            };
+            AstNode::new(kind, span)
+        }
+        TokenKind::Operator(op) => match op {
+            Operator::Parenthesis(paren) => match paren {
+                Parenthesis::LParen => parse_parens(tokens, span)?,
+                Parenthesis::RParen => {
+                    return Err(ParseError::new(ParseErrorKind::ExpectedExpression, span))
+                }
+                Parenthesis::LBracket => {
+                    let (items, end) = parse_delimited(
+                        tokens,
+                        Operator::Parenthesis(Parenthesis::RBracket),
+                        parse_assignment,
+                    )?;
+                    AstNode::new(AstKind::List(items), span.to(end))
+                }
+                _ => {
+                    return Err(ParseError::new(
+                        ParseErrorKind::UnexpectedToken(token.kind.to_string()),
+                        span,
+                    ))
+                }
+            },
+            _ => {
+                return Err(ParseError::new(
+                    ParseErrorKind::UnexpectedToken(token.kind.to_string()),
+                    span,
+                ))
+            }
+        },
+        TokenKind::String(s) => AstNode::new(AstKind::String(s.clone()), span),
+        TokenKind::Char(c) => AstNode::new(AstKind::Char(*c), span),
+        TokenKind::Newline | TokenKind::DocComment(_) => {
+            return Err(ParseError::new(
+                ParseErrorKind::UnexpectedToken(token.kind.to_string()),
+                span,
+            ))
+        }
+        TokenKind::Eof => return Err(ParseError::new(ParseErrorKind::UnexpectedEof, span)),
+    };
+
+    Ok(atom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{lexer::lex, span::Position};
+
+    fn parse_source(source: &str) -> Result<AstNode, ParseError> {
+        parse(lex(source, Position::default()).unwrap())
+    }
+
+    #[test]
+    fn duplicate_params() {
+        for (source, name, column) in [("fn f(a, b, a) {}", "a", 12), ("|x, x| x", "x", 5)] {
+            let err = parse_source(source).unwrap_err();
+            assert_eq!(err.kind, ParseErrorKind::DuplicateParam(name.to_string()));
+            assert_eq!(err.span.start.column, column);
+        }
+    }
+
+    /// Parses `source` and names what the `{` of its only statement starts,
+    /// looking into the first arm of a `match`.
+    fn braces_kind(source: &str) -> &'static str {
+        let statement = match parse_source(source).unwrap().kind {
+            AstKind::Program(mut statements) => statements.remove(0),
+            kind => panic!("{:?}", kind),
+        };
+        let node = match statement.kind {
+            AstKind::Match(_, mut arms) => arms.remove(0).body,
+            _ => statement,
+        };
+        match node.kind {
+            AstKind::Block(_) => "block",
+            AstKind::Map(_) => "map",
+            AstKind::Set(_) => "set",
+            kind => panic!("{}: {:?}", source, kind),
+        }
+    }
+
+    #[test]
+    fn braces() {
+        for (source, kind) in [
+            ("{}", "map"),
+            ("{,}", "set"),
+            ("{ x }", "block"),
+            ("{ x, y }", "set"),
+            ("{ x: y }", "map"),
+            ("{ a ? b : c }", "block"),
+            ("{ x | y, z }", "set"),
+            ("{ |a, b| a + b }", "block"),
+            ("{ |a| a, |b| b }", "set"),
+            ("{ let f = |a, b| a + b; f(1, 2) }", "block"),
+            ("{ return (1, 2) }", "block"),
+            (
+                "match x { _ => { let f = |a, b| a + b; f(1, 2) } }",
+                "block",
+            ),
+        ] {
+            assert_eq!(braces_kind(source), kind, "{}", source);
+        }
+    }
+
+    #[test]
+    fn doc_comments_are_skipped() {
+        let source = "/// Adds one.\nfn inc(x) {\n    /// Inside.\n    x + 1\n}";
+        match parse_source(source).unwrap().kind {
+            AstKind::Program(statements) => assert_eq!(statements.len(), 1),
+            kind => panic!("{:?}", kind),
+        }
+    }
+}
@@ -1,46 +1,195 @@
-#[derive(Debug, Clone, Copy)]
-pub enum Parenthesis {
-    LParen,
-    RParen,
-    LBracket,
-    RBracket,
-    LCurly,
-    RCurly,
-}
-
-#[derive(Debug, Clone, Copy)]
-pub enum Operator {
-    Equals,
-    Plus,
-    Minus,
-    Star,
-    StarStar,
-    Slash,
-    Percent,
-    Colon,
-    Semicolon,
-    Exclamation,
-    Pipe,
-    Ampersand,
-    Caret,
-    Greater,
-    Less,
-    GreaterGreater,
-    LessLess,
-    GreaterEquals,
-    LessEquals,
-    EqualsEquals,
-    ExclamationEquals,
-    Dot,
-    Parenthesis(Parenthesis),
-}
-
-#[derive(Debug)]
-pub enum Token {
-    Number(f64),
-    Char(char),
-    String(String),
-    Ident(String),
-    Operator(Operator),
-    Eof,
-}
+use std::fmt::Display;
+
+use super::span::Span;
+use crate::stdlib::bigint::BigInt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Parenthesis {
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LCurly,
+    RCurly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Equals,
+    Plus,
+    Minus,
+    Star,
+    StarStar,
+    Slash,
+    Percent,
+    Colon,
+    Semicolon,
+    Exclamation,
+    Pipe,
+    PipePipe,
+    Ampersand,
+    AmpersandAmpersand,
+    Caret,
+    Greater,
+    Less,
+    GreaterGreater,
+    LessLess,
+    GreaterEquals,
+    LessEquals,
+    EqualsEquals,
+    ExclamationEquals,
+    Dot,
+    DotDot,
+    DotDotEquals,
+    Comma,
+    PlusEquals,
+    MinusEquals,
+    StarEquals,
+    StarStarEquals,
+    SlashEquals,
+    PercentEquals,
+    PipeEquals,
+    AmpersandEquals,
+    CaretEquals,
+    LessLessEquals,
+    GreaterGreaterEquals,
+    Question,
+    QuestionQuestion,
+    QuestionDot,
+    FatArrow,
+    Parenthesis(Parenthesis),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TokenKind {
+    Number(f64),
+    Int(i64),
+    BigInt(BigInt),
+    Char(char),
+    String(String),
+    Ident(String),
+    Operator(Operator),
+    /// `///` comment, kept for tooling and skipped by the parser.
+    DocComment(String),
+    Newline,
+    Eof,
+}
+
+#[derive(Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+impl Operator {
+    /// The compound assignment form of a binary operator, `+` gives `+=`.
+    pub fn compound(self) -> Option<Operator> {
+        Some(match self {
+            Operator::Plus => Operator::PlusEquals,
+            Operator::Minus => Operator::MinusEquals,
+            Operator::Star => Operator::StarEquals,
+            Operator::StarStar => Operator::StarStarEquals,
+            Operator::Slash => Operator::SlashEquals,
+            Operator::Percent => Operator::PercentEquals,
+            Operator::Pipe => Operator::PipeEquals,
+            Operator::Ampersand => Operator::AmpersandEquals,
+            Operator::Caret => Operator::CaretEquals,
+            Operator::LessLess => Operator::LessLessEquals,
+            Operator::GreaterGreater => Operator::GreaterGreaterEquals,
+            _ => return None,
+        })
+    }
+
+    /// The binary operator applied by a compound assignment, `+=` gives `+`.
+    pub fn binary(self) -> Option<Operator> {
+        Some(match self {
+            Operator::PlusEquals => Operator::Plus,
+            Operator::MinusEquals => Operator::Minus,
+            Operator::StarEquals => Operator::Star,
+            Operator::StarStarEquals => Operator::StarStar,
+            Operator::SlashEquals => Operator::Slash,
+            Operator::PercentEquals => Operator::Percent,
+            Operator::PipeEquals => Operator::Pipe,
+            Operator::AmpersandEquals => Operator::Ampersand,
+            Operator::CaretEquals => Operator::Caret,
+            Operator::LessLessEquals => Operator::LessLess,
+            Operator::GreaterGreaterEquals => Operator::GreaterGreater,
+            _ => return None,
+        })
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            Operator::Equals => "=",
+            Operator::Plus => "+",
+            Operator::Minus => "-",
+            Operator::Star => "*",
+            Operator::StarStar => "**",
+            Operator::Slash => "/",
+            Operator::Percent => "%",
+            Operator::Colon => ":",
+            Operator::Semicolon => ";",
+            Operator::Exclamation => "!",
+            Operator::Pipe => "|",
+            Operator::PipePipe => "||",
+            Operator::Ampersand => "&",
+            Operator::AmpersandAmpersand => "&&",
+            Operator::Caret => "^",
+            Operator::Greater => ">",
+            Operator::Less => "<",
+            Operator::GreaterGreater => ">>",
+            Operator::LessLess => "<<",
+            Operator::GreaterEquals => ">=",
+            Operator::LessEquals => "<=",
+            Operator::EqualsEquals => "==",
+            Operator::ExclamationEquals => "!=",
+            Operator::Dot => ".",
+            Operator::DotDot => "..",
+            Operator::DotDotEquals => "..=",
+            Operator::Comma => ",",
+            Operator::PlusEquals => "+=",
+            Operator::MinusEquals => "-=",
+            Operator::StarEquals => "*=",
+            Operator::StarStarEquals => "**=",
+            Operator::SlashEquals => "/=",
+            Operator::PercentEquals => "%=",
+            Operator::PipeEquals => "|=",
+            Operator::AmpersandEquals => "&=",
+            Operator::CaretEquals => "^=",
+            Operator::LessLessEquals => "<<=",
+            Operator::GreaterGreaterEquals => ">>=",
+            Operator::Question => "?",
+            Operator::QuestionQuestion => "??",
+            Operator::QuestionDot => "?.",
+            Operator::FatArrow => "=>",
+            Operator::Parenthesis(paren) => match paren {
+                Parenthesis::LParen => "(",
+                Parenthesis::RParen => ")",
+                Parenthesis::LBracket => "[",
+                Parenthesis::RBracket => "]",
+                Parenthesis::LCurly => "{",
+                Parenthesis::RCurly => "}",
+            },
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKind::Number(n) => write!(f, "`{}`", n),
+            TokenKind::Int(n) => write!(f, "`{}`", n),
+            TokenKind::BigInt(n) => write!(f, "`{}`", n),
+            TokenKind::Char(c) => write!(f, "{:?}", c),
+            TokenKind::String(s) => write!(f, "{:?}", s),
+            TokenKind::Ident(ident) => write!(f, "`{}`", ident),
+            TokenKind::Operator(op) => write!(f, "`{}`", op),
+            TokenKind::DocComment(text) => write!(f, "doc comment `///{}`", text),
+            TokenKind::Newline => write!(f, "newline"),
+            TokenKind::Eof => write!(f, "EOF"),
+        }
+    }
+}
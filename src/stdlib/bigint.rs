@@ -0,0 +1,596 @@
+use std::{
+    cmp::Ordering,
+    convert::TryFrom,
+    fmt::Display,
+    ops::{Add, BitAnd, BitOr, BitXor, Mul, Neg, Sub},
+};
+
+/// Arbitrary-precision integer in sign-magnitude form. The magnitude is stored
+/// as little-endian base 2^32 limbs without trailing zeros, so zero is an empty
+/// vector and is never negative.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+    fn from_parts(negative: bool, mut limbs: Vec<u32>) -> Self {
+        trim(&mut limbs);
+        Self {
+            negative: negative && !limbs.is_empty(),
+            limbs,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn from_str_radix(digits: &str, radix: u32) -> Option<Self> {
+        let (negative, digits) = match digits.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, digits),
+        };
+        if digits.is_empty() {
+            return None;
+        }
+
+        let mut limbs = vec![];
+        for ch in digits.chars() {
+            mul_add_small(&mut limbs, radix, ch.to_digit(radix)?);
+        }
+        Some(Self::from_parts(negative, limbs))
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.limbs.len() > 2 {
+            return None;
+        }
+        let magnitude = self
+            .limbs
+            .iter()
+            .rev()
+            .fold(0_u64, |acc, &limb| (acc << 32) | limb as u64);
+        if self.negative {
+            // `i64::MIN` has no positive counterpart
+            match magnitude {
+                m if m <= i64::MAX as u64 => Some(-(m as i64)),
+                m if m == i64::MIN.unsigned_abs() => Some(i64::MIN),
+                _ => None,
+            }
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self
+            .limbs
+            .iter()
+            .rev()
+            .fold(0.0, |acc, &limb| acc * 4_294_967_296.0 + limb as f64);
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    /// Exact value of `n`, `None` if it's infinite, NaN or has a fractional
+    /// part.
+    pub fn from_f64(n: f64) -> Option<Self> {
+        if !n.is_finite() || n.fract() != 0.0 {
+            return None;
+        }
+        if n.abs() < i64::MAX as f64 {
+            return Some(BigInt::from(n as i64));
+        }
+        // at least 2^63, so the value is the mantissa shifted to the left
+        let bits = n.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as u32 - 1075;
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let magnitude = BigInt::from(mantissa as i64).shl(exponent);
+        Some(if n < 0.0 { -&magnitude } else { magnitude })
+    }
+
+    pub fn pow(&self, mut exp: u32) -> Self {
+        let mut result = BigInt::from(1);
+        let mut base = self.clone();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = &result * &base;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+
+    /// Truncating division and remainder, same as `/` and `%` on Rust
+    /// integers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rhs` is zero.
+    pub fn div_rem(&self, rhs: &Self) -> (Self, Self) {
+        assert!(!rhs.is_zero(), "attempt to divide by zero");
+        let (quotient, remainder) = div_rem_magnitude(&self.limbs, &rhs.limbs);
+        (
+            Self::from_parts(self.negative != rhs.negative, quotient),
+            Self::from_parts(self.negative, remainder),
+        )
+    }
+
+    pub fn shl(&self, bits: u32) -> Self {
+        let bit_shift = bits % 32;
+        let mut limbs = vec![0; (bits / 32) as usize];
+        let mut carry = 0;
+        for &limb in &self.limbs {
+            if bit_shift == 0 {
+                limbs.push(limb);
+            } else {
+                limbs.push((limb << bit_shift) | carry);
+                carry = limb >> (32 - bit_shift);
+            }
+        }
+        limbs.push(carry);
+        Self::from_parts(self.negative, limbs)
+    }
+
+    /// Arithmetic shift, rounds towards negative infinity like `>>` on `i64`.
+    pub fn shr(&self, bits: u32) -> Self {
+        let limb_shift = (bits / 32) as usize;
+        if limb_shift >= self.limbs.len() {
+            return if self.negative {
+                BigInt::from(-1)
+            } else {
+                BigInt::default()
+            };
+        }
+
+        let bit_shift = bits % 32;
+        let rest = &self.limbs[limb_shift..];
+        let mut lost = self.limbs[..limb_shift].iter().any(|&limb| limb != 0);
+        let mut limbs = Vec::with_capacity(rest.len());
+        if bit_shift == 0 {
+            limbs.extend_from_slice(rest);
+        } else {
+            lost |= rest[0] & ((1 << bit_shift) - 1) != 0;
+            for (i, &limb) in rest.iter().enumerate() {
+                let high = rest.get(i + 1).map_or(0, |&next| next << (32 - bit_shift));
+                limbs.push((limb >> bit_shift) | high);
+            }
+        }
+
+        let result = Self::from_parts(self.negative, limbs);
+        if self.negative && lost {
+            &result - &BigInt::from(1)
+        } else {
+            result
+        }
+    }
+
+    /// Applies `op` to the limbs of both values in two's complement, which is
+    /// how bitwise operators treat negative numbers on `i64` too.
+    fn bitwise(&self, rhs: &Self, op: fn(u32, u32) -> u32) -> Self {
+        // one more limb than either magnitude needs leaves room for the sign
+        let len = self.limbs.len().max(rhs.limbs.len()) + 1;
+        let (a, b) = (self.twos_complement(len), rhs.twos_complement(len));
+        let mut limbs: Vec<u32> = a.iter().zip(&b).map(|(&a, &b)| op(a, b)).collect();
+        let negative = limbs[len - 1] >> 31 == 1;
+        if negative {
+            negate_limbs(&mut limbs);
+        }
+        Self::from_parts(negative, limbs)
+    }
+
+    fn twos_complement(&self, len: usize) -> Vec<u32> {
+        let mut limbs = self.limbs.clone();
+        limbs.resize(len, 0);
+        if self.negative {
+            negate_limbs(&mut limbs);
+        }
+        limbs
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(n: i64) -> Self {
+        let magnitude = n.unsigned_abs();
+        Self::from_parts(n < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl<'a> Add<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        if self.negative == rhs.negative {
+            return BigInt::from_parts(self.negative, add_magnitude(&self.limbs, &rhs.limbs));
+        }
+        match cmp_magnitude(&self.limbs, &rhs.limbs) {
+            Ordering::Less => {
+                BigInt::from_parts(rhs.negative, sub_magnitude(&rhs.limbs, &self.limbs))
+            }
+            _ => BigInt::from_parts(self.negative, sub_magnitude(&self.limbs, &rhs.limbs)),
+        }
+    }
+}
+
+impl<'a> Sub<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + &-rhs
+    }
+}
+
+impl<'a> Mul<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        BigInt::from_parts(
+            self.negative != rhs.negative,
+            mul_magnitude(&self.limbs, &rhs.limbs),
+        )
+    }
+}
+
+impl<'a> BitAnd<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        self.bitwise(rhs, |a, b| a & b)
+    }
+}
+
+impl<'a> BitOr<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.bitwise(rhs, |a, b| a | b)
+    }
+}
+
+impl<'a> BitXor<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        self.bitwise(rhs, |a, b| a ^ b)
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> Self::Output {
+        BigInt::from_parts(!self.negative, self.limbs.clone())
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.limbs, &other.limbs),
+            (true, true) => cmp_magnitude(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // peel off base 10^9 chunks, least significant first
+        let mut limbs = self.limbs.clone();
+        let mut chunks = vec![];
+        while !limbs.is_empty() {
+            chunks.push(div_small(&mut limbs, 1_000_000_000));
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        if let Some(first) = chunks.next() {
+            write!(f, "{}", first)?;
+        }
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+fn trim(limbs: &mut Vec<u32>) {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut result = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0;
+    for (i, &limb) in long.iter().enumerate() {
+        let sum = limb as u64 + *short.get(i).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    result.push(carry as u32);
+    trim(&mut result);
+    result
+}
+
+/// `a - b`, `a` must not be smaller than `b`.
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i, &limb) in a.iter().enumerate() {
+        let mut diff = limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += 1 << 32;
+            borrow = 1;
+        }
+        result.push(diff as u32);
+    }
+    trim(&mut result);
+    result
+}
+
+/// Two's complement negation in place, inverts and adds one.
+fn negate_limbs(limbs: &mut [u32]) {
+    let mut carry = true;
+    for limb in limbs.iter_mut() {
+        let (sum, overflowed) = (!*limb).overflowing_add(carry as u32);
+        *limb = sum;
+        carry = overflowed;
+    }
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, &y) in b.iter().enumerate() {
+            let t = x as u64 * y as u64 + result[i + j] as u64 + carry;
+            result[i + j] = t as u32;
+            carry = t >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+    trim(&mut result);
+    result
+}
+
+fn mul_add_small(limbs: &mut Vec<u32>, mul: u32, add: u32) {
+    let mut carry = add as u64;
+    for limb in limbs.iter_mut() {
+        let t = *limb as u64 * mul as u64 + carry;
+        *limb = t as u32;
+        carry = t >> 32;
+    }
+    if carry != 0 {
+        limbs.push(carry as u32);
+    }
+}
+
+/// Divides in place by a single limb, returns the remainder.
+fn div_small(limbs: &mut Vec<u32>, divisor: u32) -> u32 {
+    let mut rem = 0;
+    for limb in limbs.iter_mut().rev() {
+        let cur = (rem << 32) | *limb as u64;
+        *limb = (cur / divisor as u64) as u32;
+        rem = cur % divisor as u64;
+    }
+    trim(limbs);
+    rem as u32
+}
+
+fn div_rem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_magnitude(a, b) == Ordering::Less {
+        return (vec![], a.to_vec());
+    }
+    if let [divisor] = b {
+        let mut quotient = a.to_vec();
+        let rem = div_small(&mut quotient, *divisor);
+        return (quotient, vec![rem]);
+    }
+
+    // plain binary long division, one bit of `a` at a time
+    let mut quotient = vec![0; a.len()];
+    let mut rem: Vec<u32> = vec![];
+    for i in (0..a.len() * 32).rev() {
+        let mut carry = (a[i / 32] >> (i % 32)) & 1;
+        for limb in rem.iter_mut() {
+            let next = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = next;
+        }
+        if carry != 0 {
+            rem.push(carry);
+        }
+        if cmp_magnitude(&rem, b) != Ordering::Less {
+            rem = sub_magnitude(&rem, b);
+            quotient[i / 32] |= 1 << (i % 32);
+        }
+    }
+    (quotient, rem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Values around limb and `i64` boundaries, all within `i128`.
+    const VALUES: [i128; 16] = [
+        0,
+        1,
+        -1,
+        7,
+        -7,
+        u32::MAX as i128,
+        1 << 32,
+        -(1 << 32),
+        1_000_000_001,
+        i64::MAX as i128,
+        i64::MIN as i128,
+        (1 << 64) + 3,
+        -(1 << 64) - 3,
+        1_000_000_000_000_000_000_000_000_007,
+        -123_456_789_012_345_678_901_234_567,
+        (1 << 100) - 1,
+    ];
+
+    fn big(n: i128) -> BigInt {
+        BigInt::from_str_radix(&n.to_string(), 10).unwrap()
+    }
+
+    #[test]
+    fn display_pads_inner_chunks() {
+        for n in VALUES {
+            assert_eq!(big(n).to_string(), n.to_string());
+        }
+        assert_eq!(
+            big(1_000_000_000_000_000_001).to_string(),
+            "1000000000000000001"
+        );
+        assert_eq!(big(-5_000_000_000).to_string(), "-5000000000");
+    }
+
+    #[test]
+    fn arithmetic_matches_i128() {
+        for a in VALUES {
+            for b in VALUES {
+                assert_eq!(&big(a) + &big(b), big(a + b), "{} + {}", a, b);
+                assert_eq!(&big(a) - &big(b), big(a - b), "{} - {}", a, b);
+                assert_eq!(big(a).cmp(&big(b)), a.cmp(&b), "{} <=> {}", a, b);
+                if let Some(product) = a.checked_mul(b) {
+                    assert_eq!(&big(a) * &big(b), big(product), "{} * {}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn long_division_truncates_like_i128() {
+        for a in VALUES {
+            for b in VALUES.iter().copied().filter(|&b| b != 0) {
+                let (quotient, remainder) = big(a).div_rem(&big(b));
+                assert_eq!(quotient, big(a / b), "{} / {}", a, b);
+                assert_eq!(remainder, big(a % b), "{} % {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn shifts_match_i128() {
+        for n in VALUES {
+            for bits in [0, 1, 5, 31, 32, 33, 64, 100] {
+                assert_eq!(big(n).shr(bits), big(n >> bits), "{} >> {}", n, bits);
+            }
+            // past the last limb only the sign is left
+            assert_eq!(big(n).shr(200), big(if n < 0 { -1 } else { 0 }));
+            // the largest value still fits into `i128` after this
+            for bits in [0, 1, 5, 26] {
+                assert_eq!(big(n).shl(bits), big(n << bits), "{} << {}", n, bits);
+            }
+        }
+    }
+
+    #[test]
+    fn to_i64_at_the_limits() {
+        assert_eq!(big(i64::MIN as i128).to_i64(), Some(i64::MIN));
+        assert_eq!(big(i64::MAX as i128).to_i64(), Some(i64::MAX));
+        assert_eq!(big(i64::MIN as i128 - 1).to_i64(), None);
+        assert_eq!(big(i64::MAX as i128 + 1).to_i64(), None);
+        assert_eq!(big(-(1 << 64)).to_i64(), None);
+    }
+
+    #[test]
+    fn from_f64_is_exact() {
+        assert_eq!(BigInt::from_f64(-42.0), Some(big(-42)));
+        assert_eq!(BigInt::from_f64(2f64.powi(63)), Some(big(1 << 63)));
+        assert_eq!(BigInt::from_f64(-(2f64.powi(64))), Some(big(-(1 << 64))));
+        assert_eq!(BigInt::from_f64(2f64.powi(100) * 3.0), Some(big(3 << 100)));
+        assert_eq!(
+            BigInt::from_f64(1e20),
+            Some(big(100_000_000_000_000_000_000))
+        );
+        for n in [0.5, -1.5, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(BigInt::from_f64(n), None, "{}", n);
+        }
+    }
+
+    #[test]
+    fn bitwise_matches_i128() {
+        for a in VALUES {
+            for b in VALUES {
+                assert_eq!(&big(a) & &big(b), big(a & b), "{} & {}", a, b);
+                assert_eq!(&big(a) | &big(b), big(a | b), "{} | {}", a, b);
+                assert_eq!(&big(a) ^ &big(b), big(a ^ b), "{} ^ {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn carries_cross_limbs() {
+        let limb = u32::MAX as i128;
+        assert_eq!(&big(limb) + &big(1), big(1 << 32));
+        assert_eq!(&big((1 << 64) - 1) + &big(1), big(1 << 64));
+        assert_eq!(&big(1 << 64) - &big(1), big((1 << 64) - 1));
+        assert_eq!(&big(1 << 96) - &big(1), big((1 << 96) - 1));
+        assert_eq!(&big(limb) * &big(limb), big(limb * limb));
+        assert_eq!(
+            &big((1 << 64) - 1) * &big((1 << 64) - 1),
+            BigInt::from_str_radix("340282366920938463426481119284349108225", 10).unwrap()
+        );
+    }
+
+    #[test]
+    fn signs() {
+        // zero is never negative, however it's made
+        for zero in [
+            &big(5) + &big(-5),
+            &big(-5) - &big(-5),
+            &big(-5) * &big(0),
+            -&big(0),
+            big(-3).div_rem(&big(7)).0,
+            big(-14).div_rem(&big(7)).1,
+        ] {
+            assert!(!zero.is_negative());
+            assert_eq!(zero, BigInt::default());
+        }
+        assert_eq!(&big(-3) + &big(5), big(2));
+        assert_eq!(&big(3) + &big(-5), big(-2));
+        assert_eq!(&big(-3) - &big(-5), big(2));
+        assert_eq!(&big(-3) * &big(-5), big(15));
+        assert!(big(-(1 << 64)) < big(-1));
+        assert!(big(-1) < big(0));
+        assert!(big(-(1 << 64)) < big(1 << 64));
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    fmt::{Debug, Display},
+    rc::Rc,
+};
+
+use super::{iter::ObjectIterator, object::Object};
+use crate::{
+    error::RuntimeError,
+    parse::{interpreter::call, parser::AstNode, span::Span, Context},
+};
+
+/// User-defined function together with the scope it was defined in. Variables
+/// of that scope are captured by reference, the function sees later changes to
+/// them and can change them itself.
+pub struct Function {
+    /// `None` for lambdas.
+    pub name: Option<String>,
+    /// Names of the parameters and where they are declared.
+    pub params: Vec<(String, Span)>,
+    pub body: Rc<AstNode>,
+    pub env: Context,
+    pub span: Span,
+}
+
+// The environment usually contains the function itself, printing it would
+// never end.
+impl Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let params: Vec<&str> = self.params.iter().map(|(name, _)| name.as_str()).collect();
+        write!(f, "Function({:?}, {:?})", self.name, params)
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "<lambda>"),
+        }
+    }
+}
+
+/// Iterator made by `f.iter()`: every item is the result of calling `f`
+/// without arguments, the first `null` ends it.
+pub struct FunctionIter(Option<Rc<Function>>);
+
+impl FunctionIter {
+    pub fn new(function: Rc<Function>) -> Self {
+        Self(Some(function))
+    }
+}
+
+impl ObjectIterator for FunctionIter {
+    fn next(&mut self) -> Result<Option<Object>, RuntimeError> {
+        let function = match &self.0 {
+            Some(function) => Object::Function(function.clone()),
+            None => return Ok(None),
+        };
+        match call(&function, vec![])? {
+            Object::Null => {
+                // don't call the function again once it's done
+                self.0 = None;
+                Ok(None)
+            }
+            item => Ok(Some(item)),
+        }
+    }
+}
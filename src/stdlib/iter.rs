@@ -0,0 +1,44 @@
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
+
+use super::object::Object;
+use crate::error::{RuntimeError, RuntimeErrorKind};
+
+/// Iteration protocol: produces objects one at a time until it returns
+/// `Ok(None)`. Every Rust iterator over `Object`s takes part automatically,
+/// other types (e.g. `FunctionIter`, driven by user code) implement it
+/// directly.
+pub trait ObjectIterator {
+    fn next(&mut self) -> Result<Option<Object>, RuntimeError>;
+}
+
+impl<I: Iterator<Item = Object>> ObjectIterator for I {
+    fn next(&mut self) -> Result<Option<Object>, RuntimeError> {
+        Ok(Iterator::next(self))
+    }
+}
+
+/// Shared handle to an iterator, copies of it advance together.
+#[derive(Clone)]
+pub struct Iter(Rc<RefCell<dyn ObjectIterator>>);
+
+impl Iter {
+    pub fn new(iter: impl ObjectIterator + 'static) -> Self {
+        Self(Rc::new(RefCell::new(iter)))
+    }
+
+    pub fn next(&self) -> Result<Option<Object>, RuntimeError> {
+        // a user function driving the iterator may try to advance it too
+        match self.0.try_borrow_mut() {
+            Ok(mut iter) => iter.next(),
+            Err(_) => Err(RuntimeError::new(RuntimeErrorKind::InvalidValue(
+                "Iterator can't be advanced while it produces an item".to_string(),
+            ))),
+        }
+    }
+}
+
+impl Debug for Iter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Iter({:p})", Rc::as_ptr(&self.0))
+    }
+}
@@ -0,0 +1,138 @@
+use std::{collections::HashMap, convert::TryFrom};
+
+use super::{bigint::BigInt, object::Object, ops::TypeOf};
+use crate::error::{RuntimeError, RuntimeErrorKind};
+
+/// Hashable form of an `Object` used as a map key. Two objects give the same
+/// key exactly when they are equal by `PartialEq for Object`, so `1` and `1.0`
+/// are the same key. `NaN` isn't equal to itself and is rejected, as are
+/// mutable objects like lists and maps. Tuples are keys if their items are.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    Null,
+    Bool(bool),
+    /// `Int`s and `Number`s with an integral value that fits into `i64`.
+    Int(i64),
+    /// `BigInt`s and larger integral `Number`s.
+    BigInt(BigInt),
+    /// Bits of any other `Number`, i.e. infinities and non-integral values.
+    Float(u64),
+    Char(char),
+    String(String),
+    Tuple(Vec<Key>),
+}
+
+impl TryFrom<&Object> for Key {
+    type Error = RuntimeError;
+
+    fn try_from(object: &Object) -> Result<Self, Self::Error> {
+        Ok(match object {
+            Object::Null => Key::Null,
+            Object::Bool(b) => Key::Bool(*b),
+            Object::Int(n) => Key::Int(*n),
+            Object::BigInt(n) => Key::BigInt((**n).clone()),
+            Object::Number(n) if n.is_nan() => {
+                return Err(RuntimeError::new(RuntimeErrorKind::InvalidValue(
+                    "NaN can't be a map key or a set item".to_string(),
+                )))
+            }
+            // `i64::MAX as f64` is 2^63 and doesn't fit, hence the `<`
+            Object::Number(n)
+                if n.fract() == 0.0 && *n >= i64::MIN as f64 && *n < i64::MAX as f64 =>
+            {
+                Key::Int(*n as i64)
+            }
+            Object::Number(n) => match BigInt::from_f64(*n) {
+                Some(n) => Key::BigInt(n),
+                // `-0.0 == 0.0` is handled above
+                None => Key::Float(n.to_bits()),
+            },
+            Object::Char(c) => Key::Char(*c),
+            Object::String(s) => Key::String(s.borrow().clone()),
+            Object::Tuple(items) => {
+                Key::Tuple(items.iter().map(Key::try_from).collect::<Result<_, _>>()?)
+            }
+            _ => {
+                return Err(RuntimeError::new(RuntimeErrorKind::Unhashable(
+                    object.r#typeof(),
+                )))
+            }
+        })
+    }
+}
+
+/// Map that remembers the order in which keys were first inserted.
+#[derive(Debug, Default, Clone)]
+pub struct Map {
+    indices: HashMap<Key, usize>,
+    /// The original key objects along with the values, in insertion order.
+    entries: Vec<(Object, Object)>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, key: &Object) -> Result<Option<&Object>, RuntimeError> {
+        let key = Key::try_from(key)?;
+        Ok(self.indices.get(&key).map(|&i| &self.entries[i].1))
+    }
+
+    pub fn contains(&self, key: &Object) -> Result<bool, RuntimeError> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Sets the value of `key`. A key that is already present keeps its place
+    /// in the order.
+    pub fn insert(&mut self, key: Object, value: Object) -> Result<(), RuntimeError> {
+        let hashed = Key::try_from(&key)?;
+        match self.indices.get(&hashed) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                self.indices.insert(hashed, self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: &Object) -> Result<Option<Object>, RuntimeError> {
+        let i = match self.indices.remove(&Key::try_from(key)?) {
+            Some(i) => i,
+            None => return Ok(None),
+        };
+        let (_, value) = self.entries.remove(i);
+        for index in self.indices.values_mut() {
+            if *index > i {
+                *index -= 1;
+            }
+        }
+        Ok(Some(value))
+    }
+
+    /// Whether both maps have the same keys, comparing their values with
+    /// `eq`.
+    pub fn eq_by(&self, other: &Map, mut eq: impl FnMut(&Object, &Object) -> bool) -> bool {
+        self.len() == other.len()
+            && self.iter().all(|(key, value)| match other.get(key) {
+                Ok(Some(other_value)) => eq(value, other_value),
+                _ => false,
+            })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Object, Object)> {
+        self.entries.iter()
+    }
+}
+
+impl PartialEq for Map {
+    /// Maps are equal when they have the same entries, in any order.
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_by(other, |a, b| a == b)
+    }
+}
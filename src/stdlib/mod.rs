@@ -1,2 +1,8 @@
-pub mod object;
-pub mod ops;
+pub mod bigint;
+pub mod function;
+pub mod iter;
+pub mod map;
+pub mod object;
+pub mod ops;
+pub mod range;
+pub mod set;
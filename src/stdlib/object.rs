@@ -1,575 +1,1323 @@
-use std::{
-    cell::RefCell,
-    fmt::{Debug, Display},
-    ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Not, Rem, Shl, Shr, Sub},
-    rc::Rc,
-};
-
-use super::ops::{Pow, TypeOf};
-
-#[derive(Debug, Clone)]
-pub enum Object {
-    Number(f64),
-    Bool(bool),
-    String(Rc<RefCell<String>>),
-    Char(char),
-}
-
-impl<'a> Add<&'a Object> for &'a Object {
-    type Output = Result<Object, String>;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        Ok(match (self, rhs) {
-            (Object::Number(a), Object::Number(b)) => Object::Number(a + b),
-            (Object::Number(n), Object::Bool(b)) => Object::Number(match b {
-                true => n + 1.0,
-                false => *n,
-            }),
-            (Object::Bool(_), Object::Number(_)) => rhs.add(self)?,
-            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
-                (true, true) => 2.0,
-                (true, false) => 1.0,
-                (false, true) => 1.0,
-                (false, false) => 0.0,
-            }),
-            (Object::Number(n), Object::String(s)) => {
-                Object::String(Rc::new(RefCell::new(format!("{}{}", n, s.borrow()))))
-            }
-            (Object::Bool(b), Object::String(s)) => {
-                Object::String(Rc::new(RefCell::new(format!("{}{}", b, s.borrow()))))
-            }
-            (Object::String(s1), Object::String(s2)) => Object::String(Rc::new(RefCell::new(
-                format!("{}{}", s1.borrow(), s2.borrow()),
-            ))),
-            (Object::Char(c), Object::String(s)) => {
-                Object::String(Rc::new(RefCell::new(format!("{}{}", c, s.borrow()))))
-            }
-            (Object::String(s), Object::Number(n)) => {
-                Object::String(Rc::new(RefCell::new(format!("{}{}", s.borrow(), n))))
-            }
-            (Object::String(s), Object::Bool(b)) => {
-                Object::String(Rc::new(RefCell::new(format!("{}{}", s.borrow(), b))))
-            }
-            (Object::String(s), Object::Char(c)) => {
-                Object::String(Rc::new(RefCell::new(format!("{}{}", s.borrow(), c))))
-            }
-            (Object::Char(c1), Object::Char(c2)) => Object::Char((*c1 as u8 + *c2 as u8) as char),
-            (Object::Number(n), Object::Char(c)) => Object::Char((*n as u8 + *c as u8) as char),
-
-            (Object::Char(_), Object::Number(_)) => rhs.add(self)?,
-            (a, b) => {
-                return Err(format!(
-                    "Operator '{}' is not supported for types {} and {}",
-                    '+',
-                    a.r#typeof(),
-                    b.r#typeof()
-                ))
-            }
-        })
-    }
-}
-
-impl<'a> Sub<&'a Object> for &'a Object {
-    type Output = Result<Object, String>;
-
-    fn sub(self, rhs: Self) -> Self::Output {
-        Ok(match (self, rhs) {
-            (Object::Number(a), Object::Number(b)) => Object::Number(a - b),
-            (Object::Number(n), Object::Bool(b)) => Object::Number(match b {
-                true => n - 1.0,
-                false => *n,
-            }),
-            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
-                (true, true) => 0.0,
-                (true, false) => 1.0,
-                (false, true) => -1.0,
-                (false, false) => 0.0,
-            }),
-            (Object::Bool(b), Object::Number(n)) => Object::Number(match b {
-                true => 1.0 - n,
-                false => -n,
-            }),
-            (Object::String(_), Object::String(_)) => {
-                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
-            }
-            (Object::Number(_), Object::String(_)) => {
-                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
-            }
-            (Object::Bool(_), Object::String(_)) => {
-                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
-            }
-            (Object::Char(_), Object::String(_)) => {
-                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
-            }
-            (Object::String(_), Object::Number(_)) => {
-                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
-            }
-            (Object::String(_), Object::Bool(_)) => {
-                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
-            }
-            (Object::String(_), Object::Char(_)) => {
-                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
-            }
-            (Object::Char(c1), Object::Char(c2)) => Object::Char((*c1 as u8 - *c2 as u8) as char),
-            (Object::Number(n), Object::Char(c)) => {
-                Object::Char((*n as u8).wrapping_sub(*c as u8) as char)
-            }
-            (Object::Char(c), Object::Number(n)) => {
-                Object::Char((*c as u8).wrapping_sub(*n as u8) as char)
-            }
-            (a, b) => {
-                return Err(format!(
-                    "Operator '{}' is not supported for types {} and {}",
-                    '-',
-                    a.r#typeof(),
-                    b.r#typeof()
-                ))
-            }
-        })
-    }
-}
-
-impl<'a> Mul<&'a Object> for &'a Object {
-    type Output = Result<Object, String>;
-
-    fn mul(self, rhs: &'a Object) -> Self::Output {
-        Ok(match (self, rhs) {
-            (Object::Number(a), Object::Number(b)) => Object::Number(a * b),
-            (Object::Number(n), Object::Bool(b)) => Object::Number(match b {
-                true => *n,
-                false => 0.0,
-            }),
-            (Object::Bool(_), Object::Number(_)) => rhs.mul(self)?,
-            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
-                (true, true) => 1.0,
-                (true, false) => 0.0,
-                (false, true) => 0.0,
-                (false, false) => 0.0,
-            }),
-            (Object::Number(n), Object::String(s)) => {
-                if n.fract() == 0.0 {
-                    Object::String(Rc::new(RefCell::new(if n.is_sign_positive() {
-                        s.borrow().repeat(*n as usize)
-                    } else {
-                        "".to_string()
-                    })))
-                } else {
-                    return Err("Can't repeat string fractional number of times".to_string());
-                }
-            }
-            (Object::String(_), Object::Number(_)) => rhs.mul(self)?,
-            (a, b) => {
-                return Err(format!(
-                    "Operator '{}' is not supported for types {} and {}",
-                    '*',
-                    a.r#typeof(),
-                    b.r#typeof()
-                ))
-            }
-        })
-    }
-}
-
-impl<'a> Div<&'a Object> for &'a Object {
-    type Output = Result<Object, String>;
-
-    fn div(self, rhs: &'a Object) -> Self::Output {
-        Ok(match (self, rhs) {
-            (Object::Number(a), Object::Number(b)) => Object::Number(a / b),
-            (Object::Number(n), Object::Bool(b)) => Object::Number(match b {
-                true => *n,
-                false => f64::INFINITY,
-            }),
-            (Object::Bool(b), Object::Number(n)) => Object::Number(match b {
-                true => n.recip(),
-                false => 0.0,
-            }),
-            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
-                (true, true) => 1.0,
-                (true, false) => f64::INFINITY,
-                (false, true) => 0.0,
-                (false, false) => f64::INFINITY,
-            }),
-            (a, b) => {
-                return Err(format!(
-                    "Operator '{}' is not supported for types {} and {}",
-                    '/',
-                    a.r#typeof(),
-                    b.r#typeof()
-                ))
-            }
-        })
-    }
-}
-
-impl<'a> Pow<&'a Object> for &'a Object {
-    type Output = Result<Object, String>;
-
-    fn pow(self, rhs: &'a Object) -> Self::Output {
-        Ok(match (self, rhs) {
-            (Object::Number(n), Object::Number(m)) => Object::Number(if *n == 0.0 && *m == 0.0 {
-                f64::NAN
-            } else {
-                n.powf(*m)
-            }),
-            (Object::Number(n), Object::Bool(b)) => Object::Number(match (*n, *b) {
-                (n, false) if n == 0.0 => f64::NAN,
-                (_, false) => 1.0,
-                (n, true) => n,
-            }),
-            (Object::Bool(b), Object::Number(n)) => Object::Number(match (*b, *n) {
-                (false, n) if n == 0.0 => f64::NAN,
-                (false, _) => 0.0,
-                (true, _) => 1.0,
-            }),
-            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
-                (true, true) => 1.0,
-                (true, false) => 1.0,
-                (false, true) => 0.0,
-                (false, false) => f64::NAN,
-            }),
-            (a, b) => {
-                return Err(format!(
-                    "Operator '{}' is not supported for types {} and {}",
-                    "**",
-                    a.r#typeof(),
-                    b.r#typeof()
-                ))
-            }
-        })
-    }
-}
-
-impl<'a> Rem<&'a Object> for &'a Object {
-    type Output = Result<Object, String>;
-
-    fn rem(self, rhs: &'a Object) -> Self::Output {
-        Ok(match (self, rhs) {
-            (Object::Number(a), Object::Number(b)) => Object::Number(a % b),
-            (Object::Number(_), Object::Bool(b)) => Object::Number(match b {
-                true => 0.0,
-                false => f64::NAN,
-            }),
-            (Object::Bool(b), Object::Number(_)) => Object::Number(match b {
-                true => 0.0,
-                false => 1.0,
-            }),
-            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
-                (_, true) => 0.0,
-                (_, false) => f64::NAN,
-            }),
-            (a, b) => {
-                return Err(format!(
-                    "Operator '{}' is not supported for types {} and {}",
-                    '%',
-                    a.r#typeof(),
-                    b.r#typeof()
-                ))
-            }
-        })
-    }
-}
-
-impl<'a> Not for &'a Object {
-    type Output = Result<Object, String>;
-
-    fn not(self) -> Self::Output {
-        Ok(Object::Bool(match self {
-            Object::Number(n) => *n == 0.0 || n.is_nan(),
-            Object::Bool(b) => !b,
-            _ => {
-                return Err(format!(
-                    "Operator '{}' is not supported for type {}",
-                    '!',
-                    self.r#typeof()
-                ))
-            }
-        }))
-    }
-}
-
-impl<'a> BitOr<&'a Object> for &'a Object {
-    type Output = Result<Object, String>;
-
-    fn bitor(self, rhs: &'a Object) -> Self::Output {
-        Ok(match (self, rhs) {
-            (Object::Number(a), Object::Number(b)) => {
-                Object::Number(((*a as i64) | (*b as i64)) as f64)
-            }
-            (Object::Number(n), Object::Bool(b)) => Object::Number(match b {
-                true => ((*n as i64) | 1) as f64,
-                false => *n,
-            }),
-            (Object::Bool(_), Object::Number(_)) => rhs.bitor(self)?,
-            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
-                (true, true) => 1.0,
-                (true, false) => 1.0,
-                (false, true) => 1.0,
-                (false, false) => 0.0,
-            }),
-            (a, b) => {
-                return Err(format!(
-                    "Operator '{}' is not supported for types {} and {}",
-                    '|',
-                    a.r#typeof(),
-                    b.r#typeof()
-                ))
-            }
-        })
-    }
-}
-
-impl<'a> BitAnd<&'a Object> for &'a Object {
-    type Output = Result<Object, String>;
-
-    fn bitand(self, rhs: &'a Object) -> Self::Output {
-        Ok(match (self, rhs) {
-            (Object::Number(a), Object::Number(b)) => {
-                Object::Number(((*a as i64) & (*b as i64)) as f64)
-            }
-            (Object::Number(n), Object::Bool(b)) => Object::Number(match b {
-                true => ((*n as i64) & 1) as f64,
-                false => 0.0,
-            }),
-            (Object::Bool(_), Object::Number(_)) => rhs.bitand(self)?,
-            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
-                (true, true) => 1.0,
-                (true, false) => 0.0,
-                (false, true) => 0.0,
-                (false, false) => 0.0,
-            }),
-            (a, b) => {
-                return Err(format!(
-                    "Operator '{}' is not supported for types {} and {}",
-                    '&',
-                    a.r#typeof(),
-                    b.r#typeof()
-                ))
-            }
-        })
-    }
-}
-
-impl<'a> BitXor<&'a Object> for &'a Object {
-    type Output = Result<Object, String>;
-
-    fn bitxor(self, rhs: &'a Object) -> Self::Output {
-        Ok(match (self, rhs) {
-            (Object::Number(a), Object::Number(b)) => {
-                Object::Number(((*a as i64) ^ (*b as i64)) as f64)
-            }
-            (Object::Number(n), Object::Bool(b)) => Object::Number(match b {
-                true => ((*n as i64) ^ 1) as f64,
-                false => *n,
-            }),
-            (Object::Bool(_), Object::Number(_)) => rhs.bitxor(self)?,
-            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
-                (true, true) => 0.0,
-                (true, false) => 1.0,
-                (false, true) => 1.0,
-                (false, false) => 0.0,
-            }),
-            (a, b) => {
-                return Err(format!(
-                    "Operator '{}' is not supported for types {} and {}",
-                    '^',
-                    a.r#typeof(),
-                    b.r#typeof()
-                ))
-            }
-        })
-    }
-}
-
-impl<'a> Shr<&'a Object> for &'a Object {
-    type Output = Result<Object, String>;
-
-    fn shr(self, rhs: &'a Object) -> Self::Output {
-        Ok(match (self, rhs) {
-            (Object::Number(a), Object::Number(b)) => {
-                Object::Number(((*a as i64).wrapping_shr(*b as u32)) as f64)
-            }
-            (Object::Number(n), Object::Bool(b)) => Object::Number(match b {
-                true => ((*n as i64).wrapping_shr(1)) as f64,
-                false => *n,
-            }),
-            (Object::Bool(_), Object::Number(_)) => Object::Number(0.0),
-            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
-                (true, true) => 0.0,
-                (true, false) => 1.0,
-                (false, true) => 0.0,
-                (false, false) => 0.0,
-            }),
-            (a, b) => {
-                return Err(format!(
-                    "Operator '{}' is not supported for types {} and {}",
-                    ">>",
-                    a.r#typeof(),
-                    b.r#typeof()
-                ))
-            }
-        })
-    }
-}
-
-impl<'a> Shl<&'a Object> for &'a Object {
-    type Output = Result<Object, String>;
-
-    fn shl(self, rhs: &'a Object) -> Self::Output {
-        Ok(match (self, rhs) {
-            (Object::Number(a), Object::Number(b)) => {
-                Object::Number(((*a as i64).wrapping_shl(*b as u32)) as f64)
-            }
-            (Object::Number(n), Object::Bool(b)) => Object::Number(match b {
-                true => ((*n as i64).wrapping_shl(1)) as f64,
-                false => *n,
-            }),
-            (Object::Bool(b), Object::Number(n)) => Object::Number(match b {
-                true => (1_i32.wrapping_shl(*n as u32)) as f64,
-                false => 0.0,
-            }),
-            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
-                (true, true) => 2.0,
-                (true, false) => 1.0,
-                (false, true) => 0.0,
-                (false, false) => 0.0,
-            }),
-            (a, b) => {
-                return Err(format!(
-                    "Operator '{}' is not supported for types {} and {}",
-                    "<<",
-                    a.r#typeof(),
-                    b.r#typeof()
-                ))
-            }
-        })
-    }
-}
-
-impl PartialEq for Object {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Object::Number(a), Object::Number(b)) => (*a).eq(b),
-            (Object::Bool(b1), Object::Bool(b2)) => b1.eq(b2),
-            (Object::String(s1), Object::String(s2)) => s1.borrow().eq(&*s2.borrow()),
-            (Object::Char(c1), Object::Char(c2)) => c1.eq(c2),
-            _ => false,
-        }
-    }
-}
-
-impl PartialOrd for Object {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        match (self, other) {
-            (Object::Number(a), Object::Number(b)) => a.partial_cmp(b),
-            (Object::Bool(b), Object::Number(n)) => match (*b, n) {
-                (true, n) => 1.0.partial_cmp(n),
-                (false, n) => 0.0.partial_cmp(n),
-            },
-            (Object::Number(_), Object::Bool(_)) => other.partial_cmp(self),
-            (Object::Bool(b1), Object::Bool(b2)) => b1.partial_cmp(b2),
-            (Object::String(s1), Object::String(s2)) => s1.borrow().partial_cmp(&*s2.borrow()),
-            (Object::Char(c1), Object::Char(c2)) => c1.partial_cmp(c2),
-            _ => None,
-        }
-    }
-}
-
-// #[allow(unused)]
-impl Object {
-    pub fn eq(&self, other: &Object) -> Object {
-        Object::Bool(self == other)
-    }
-    pub fn ne(&self, other: &Object) -> Object {
-        Object::Bool(self != other)
-    }
-
-    pub fn gt(&self, other: &Object) -> Object {
-        Object::Bool(self > other)
-    }
-    pub fn lt(&self, other: &Object) -> Object {
-        Object::Bool(self < other)
-    }
-    pub fn gte(&self, other: &Object) -> Object {
-        Object::Bool(self >= other)
-    }
-    pub fn lte(&self, other: &Object) -> Object {
-        Object::Bool(self <= other)
-    }
-
-    #[allow(unused_variables)]
-    pub fn idx(&self, idx: isize) -> Result<Object, String> {
-        Err(format!(
-            "Object of type {} can not be indexed",
-            self.r#typeof(),
-        ))
-    }
-
-    // pub fn call(&self, params, body) -> Result<Object, String> {
-    //     Err(format!(
-    //         "Object of type {} is not callable",
-    //         self.r#typeof(),
-    //     ))
-    // }
-
-    pub fn bool(&self) -> bool {
-        match self {
-            Object::Number(n) => *n != 0.0,
-            Object::Bool(b) => *b,
-            Object::String(s) => s.borrow().is_empty(),
-            Object::Char(c) => *c != '\0',
-        }
-    }
-
-    pub fn num(&self) -> Result<f64, String> {
-        Ok(match self {
-            Object::Number(n) => *n,
-            Object::Bool(b) => {
-                if *b {
-                    1.0
-                } else {
-                    0.0
-                }
-            }
-            Object::String(s) => s.borrow().parse::<f64>().unwrap_or(f64::NAN),
-            Object::Char(c) => *c as i64 as f64,
-            _ => {
-                return Err(format!(
-                    "{} can not be converted to Number",
-                    self.r#typeof()
-                ))
-            }
-        })
-    }
-
-    pub fn str(&self) -> String {
-        self.repr()
-    }
-
-    pub fn repr(&self) -> String {
-        match self {
-            Object::Number(n) => n.to_string(),
-            Object::Bool(b) => b.to_string(),
-            Object::Char(c) => format!("{:?}", c),
-            Object::String(s) => format!("{:?}", s.borrow()),
-            _ => format!("<{} object at {:?}>", self.r#typeof(), self as *const Self),
-        }
-    }
-}
-
-impl TypeOf for &Object {
-    fn r#typeof(self) -> String {
-        match self {
-            Object::Number(_) => "Number".to_string(),
-            Object::Bool(_) => "Bool".to_string(),
-            Object::String(_) => "String".to_string(),
-            Object::Char(_) => "Char".to_string(),
-        }
-    }
-}
-
-impl Display for Object {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.repr())
-    }
-}
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    convert::TryFrom,
+    fmt::{Debug, Display},
+    ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Neg, Not, Rem, Shl, Shr, Sub},
+    rc::Rc,
+};
+
+use super::{
+    bigint::BigInt,
+    function::{Function, FunctionIter},
+    iter::Iter,
+    map::Map,
+    ops::{Pow, TypeOf},
+    range::Range,
+    set::Set,
+};
+use crate::error::{RuntimeError, RuntimeErrorKind};
+
+#[derive(Debug, Clone)]
+pub enum Object {
+    Number(f64),
+    Int(i64),
+    /// Integers that don't fit into `Int`, never holds a value that does.
+    BigInt(Rc<BigInt>),
+    Bool(bool),
+    String(Rc<RefCell<String>>),
+    Char(char),
+    /// The absence of a value, e.g. of an `if` without an `else` whose
+    /// condition is false or of a function that doesn't return anything. It's
+    /// equal only to itself, `??` and `?.` handle it, every other operator and
+    /// using it as a condition raise an error instead of coercing it.
+    Null,
+    /// Made by `.iter()`, copies share the position.
+    Iterator(Iter),
+    Range(Range),
+    Function(Rc<Function>),
+    List(Rc<RefCell<Vec<Object>>>),
+    Map(Rc<RefCell<Map>>),
+    /// Immutable sequence, unlike `List`.
+    Tuple(Rc<Vec<Object>>),
+    Set(Rc<RefCell<Set>>),
+}
+
+impl From<BigInt> for Object {
+    fn from(n: BigInt) -> Self {
+        match n.to_i64() {
+            Some(n) => Object::Int(n),
+            None => Object::BigInt(Rc::new(n)),
+        }
+    }
+}
+
+impl<'a> Add<&'a Object> for &'a Object {
+    type Output = Result<Object, RuntimeError>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Ok(match (self, rhs) {
+            (Object::Int(_) | Object::BigInt(_), Object::Int(_) | Object::BigInt(_)) => {
+                int_op(self, rhs, i64::checked_add, |a, b| a + b)
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::Number(_)) => {
+                Object::Number(self.num()?).add(rhs)?
+            }
+            (Object::Number(_), Object::Int(_) | Object::BigInt(_)) => {
+                self.add(&Object::Number(rhs.num()?))?
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::Bool(b)) => {
+                self.add(&Object::Int(*b as i64))?
+            }
+            (Object::Bool(a), Object::Int(_) | Object::BigInt(_)) => {
+                Object::Int(*a as i64).add(rhs)?
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::String(s)) => {
+                Object::String(Rc::new(RefCell::new(format!("{}{}", self, s.borrow()))))
+            }
+            (Object::String(s), Object::Int(_) | Object::BigInt(_)) => {
+                Object::String(Rc::new(RefCell::new(format!("{}{}", s.borrow(), rhs))))
+            }
+            (Object::Int(_) | Object::Number(_) | Object::Char(_), Object::Char(_))
+            | (Object::Char(_), Object::Int(_) | Object::Number(_)) => {
+                char_op(self, rhs, "+", i64::checked_add)?
+            }
+            (Object::Number(a), Object::Number(b)) => Object::Number(a + b),
+            (Object::Number(n), Object::Bool(b)) => Object::Number(match b {
+                true => n + 1.0,
+                false => *n,
+            }),
+            (Object::Bool(_), Object::Number(_)) => rhs.add(self)?,
+            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
+                (true, true) => 2.0,
+                (true, false) => 1.0,
+                (false, true) => 1.0,
+                (false, false) => 0.0,
+            }),
+            (Object::Number(n), Object::String(s)) => {
+                Object::String(Rc::new(RefCell::new(format!("{}{}", n, s.borrow()))))
+            }
+            (Object::Bool(b), Object::String(s)) => {
+                Object::String(Rc::new(RefCell::new(format!("{}{}", b, s.borrow()))))
+            }
+            (Object::String(s1), Object::String(s2)) => Object::String(Rc::new(RefCell::new(
+                format!("{}{}", s1.borrow(), s2.borrow()),
+            ))),
+            (Object::List(l1), Object::List(l2)) => Object::list(
+                l1.borrow()
+                    .iter()
+                    .chain(l2.borrow().iter())
+                    .cloned()
+                    .collect(),
+            ),
+            (Object::Tuple(t1), Object::Tuple(t2)) => {
+                Object::tuple(t1.iter().chain(t2.iter()).cloned().collect())
+            }
+            (Object::Char(c), Object::String(s)) => {
+                Object::String(Rc::new(RefCell::new(format!("{}{}", c, s.borrow()))))
+            }
+            (Object::String(s), Object::Number(n)) => {
+                Object::String(Rc::new(RefCell::new(format!("{}{}", s.borrow(), n))))
+            }
+            (Object::String(s), Object::Bool(b)) => {
+                Object::String(Rc::new(RefCell::new(format!("{}{}", s.borrow(), b))))
+            }
+            (Object::String(s), Object::Char(c)) => {
+                Object::String(Rc::new(RefCell::new(format!("{}{}", s.borrow(), c))))
+            }
+            (a, b) => {
+                return Err(RuntimeError::type_mismatch(
+                    "+",
+                    &[a.r#typeof(), b.r#typeof()],
+                ))
+            }
+        })
+    }
+}
+
+impl<'a> Sub<&'a Object> for &'a Object {
+    type Output = Result<Object, RuntimeError>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Ok(match (self, rhs) {
+            (Object::Int(_) | Object::BigInt(_), Object::Int(_) | Object::BigInt(_)) => {
+                int_op(self, rhs, i64::checked_sub, |a, b| a - b)
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::Number(_)) => {
+                Object::Number(self.num()?).sub(rhs)?
+            }
+            (Object::Number(_), Object::Int(_) | Object::BigInt(_)) => {
+                self.sub(&Object::Number(rhs.num()?))?
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::Bool(b)) => {
+                self.sub(&Object::Int(*b as i64))?
+            }
+            (Object::Bool(a), Object::Int(_) | Object::BigInt(_)) => {
+                Object::Int(*a as i64).sub(rhs)?
+            }
+            (Object::Int(_) | Object::Number(_) | Object::Char(_), Object::Char(_))
+            | (Object::Char(_), Object::Int(_) | Object::Number(_)) => {
+                char_op(self, rhs, "-", i64::checked_sub)?
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::String(_))
+            | (Object::String(_), Object::Int(_) | Object::BigInt(_)) => {
+                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
+            }
+            (Object::Number(a), Object::Number(b)) => Object::Number(a - b),
+            (Object::Number(n), Object::Bool(b)) => Object::Number(match b {
+                true => n - 1.0,
+                false => *n,
+            }),
+            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
+                (true, true) => 0.0,
+                (true, false) => 1.0,
+                (false, true) => -1.0,
+                (false, false) => 0.0,
+            }),
+            (Object::Bool(b), Object::Number(n)) => Object::Number(match b {
+                true => 1.0 - n,
+                false => -n,
+            }),
+            (Object::String(_), Object::String(_)) => {
+                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
+            }
+            (Object::Number(_), Object::String(_)) => {
+                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
+            }
+            (Object::Bool(_), Object::String(_)) => {
+                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
+            }
+            (Object::Char(_), Object::String(_)) => {
+                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
+            }
+            (Object::String(_), Object::Number(_)) => {
+                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
+            }
+            (Object::String(_), Object::Bool(_)) => {
+                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
+            }
+            (Object::String(_), Object::Char(_)) => {
+                Object::Number(self.num()?).sub(&Object::Number(rhs.num()?))?
+            }
+            (Object::Set(a), Object::Set(b)) => Object::set(a.borrow().difference(&b.borrow())?),
+            (a, b) => {
+                return Err(RuntimeError::type_mismatch(
+                    "-",
+                    &[a.r#typeof(), b.r#typeof()],
+                ))
+            }
+        })
+    }
+}
+
+impl<'a> Mul<&'a Object> for &'a Object {
+    type Output = Result<Object, RuntimeError>;
+
+    fn mul(self, rhs: &'a Object) -> Self::Output {
+        Ok(match (self, rhs) {
+            (Object::Int(_) | Object::BigInt(_), Object::Int(_) | Object::BigInt(_)) => {
+                int_op(self, rhs, i64::checked_mul, |a, b| a * b)
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::Number(_)) => {
+                Object::Number(self.num()?).mul(rhs)?
+            }
+            (Object::Number(_), Object::Int(_) | Object::BigInt(_)) => {
+                self.mul(&Object::Number(rhs.num()?))?
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::Bool(b)) => {
+                self.mul(&Object::Int(*b as i64))?
+            }
+            (Object::Bool(a), Object::Int(_) | Object::BigInt(_)) => {
+                Object::Int(*a as i64).mul(rhs)?
+            }
+            (Object::Int(n), Object::String(s)) => Object::String(Rc::new(RefCell::new(
+                s.borrow().repeat((*n).max(0) as usize),
+            ))),
+            (Object::BigInt(_), Object::String(_)) => {
+                return Err(RuntimeError::new(RuntimeErrorKind::InvalidValue(
+                    "Can't repeat string that many times".to_string(),
+                )))
+            }
+            (Object::String(_), Object::Int(_) | Object::BigInt(_)) => rhs.mul(self)?,
+            (Object::Number(a), Object::Number(b)) => Object::Number(a * b),
+            (Object::Number(n), Object::Bool(b)) => Object::Number(match b {
+                true => *n,
+                false => 0.0,
+            }),
+            (Object::Bool(_), Object::Number(_)) => rhs.mul(self)?,
+            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
+                (true, true) => 1.0,
+                (true, false) => 0.0,
+                (false, true) => 0.0,
+                (false, false) => 0.0,
+            }),
+            (Object::Number(n), Object::String(s)) => {
+                if n.fract() == 0.0 {
+                    Object::String(Rc::new(RefCell::new(if n.is_sign_positive() {
+                        s.borrow().repeat(*n as usize)
+                    } else {
+                        "".to_string()
+                    })))
+                } else {
+                    return Err(RuntimeError::new(RuntimeErrorKind::InvalidValue(
+                        "Can't repeat string fractional number of times".to_string(),
+                    )));
+                }
+            }
+            (Object::String(_), Object::Number(_)) => rhs.mul(self)?,
+            (a, b) => {
+                return Err(RuntimeError::type_mismatch(
+                    "*",
+                    &[a.r#typeof(), b.r#typeof()],
+                ))
+            }
+        })
+    }
+}
+
+impl<'a> Div<&'a Object> for &'a Object {
+    type Output = Result<Object, RuntimeError>;
+
+    fn div(self, rhs: &'a Object) -> Self::Output {
+        Ok(match (self, rhs) {
+            (Object::Int(_) | Object::BigInt(_), Object::Int(0)) => {
+                return Err(RuntimeError::new(RuntimeErrorKind::DivisionByZero))
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::Int(_) | Object::BigInt(_)) => {
+                int_op(self, rhs, i64::checked_div, |a, b| a.div_rem(b).0)
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::Number(_)) => {
+                Object::Number(self.num()?).div(rhs)?
+            }
+            (Object::Number(_), Object::Int(_) | Object::BigInt(_)) => {
+                self.div(&Object::Number(rhs.num()?))?
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::Bool(b)) => {
+                self.div(&Object::Int(*b as i64))?
+            }
+            (Object::Bool(a), Object::Int(_) | Object::BigInt(_)) => {
+                Object::Int(*a as i64).div(rhs)?
+            }
+            (Object::Number(a), Object::Number(b)) => Object::Number(a / b),
+            (Object::Number(n), Object::Bool(b)) => Object::Number(match b {
+                true => *n,
+                false => f64::INFINITY,
+            }),
+            (Object::Bool(b), Object::Number(n)) => Object::Number(match b {
+                true => n.recip(),
+                false => 0.0,
+            }),
+            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
+                (true, true) => 1.0,
+                (true, false) => f64::INFINITY,
+                (false, true) => 0.0,
+                (false, false) => f64::INFINITY,
+            }),
+            (a, b) => {
+                return Err(RuntimeError::type_mismatch(
+                    "/",
+                    &[a.r#typeof(), b.r#typeof()],
+                ))
+            }
+        })
+    }
+}
+
+impl<'a> Pow<&'a Object> for &'a Object {
+    type Output = Result<Object, RuntimeError>;
+
+    fn pow(self, rhs: &'a Object) -> Self::Output {
+        Ok(match (self, rhs) {
+            (Object::Int(0), Object::Int(0)) => Object::Number(f64::NAN),
+            (Object::Int(_) | Object::BigInt(_), Object::Int(b)) => match u32::try_from(*b) {
+                Ok(exp) => match self {
+                    Object::Int(a) => a.checked_pow(exp).map(Object::Int),
+                    _ => None,
+                }
+                .unwrap_or_else(|| Object::from(to_bigint(self).pow(exp))),
+                Err(_) if *b < 0 => Object::Number(self.num()?.powf(*b as f64)),
+                Err(_) => return Err(overflow("**")),
+            },
+            (Object::Int(_) | Object::BigInt(_), Object::BigInt(_)) => return Err(overflow("**")),
+            (Object::Int(_) | Object::BigInt(_), Object::Number(_)) => {
+                Object::Number(self.num()?).pow(rhs)?
+            }
+            (Object::Number(_), Object::Int(_) | Object::BigInt(_)) => {
+                self.pow(&Object::Number(rhs.num()?))?
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::Bool(b)) => {
+                self.pow(&Object::Int(*b as i64))?
+            }
+            (Object::Bool(a), Object::Int(_) | Object::BigInt(_)) => {
+                Object::Int(*a as i64).pow(rhs)?
+            }
+            (Object::Number(n), Object::Number(m)) => Object::Number(if *n == 0.0 && *m == 0.0 {
+                f64::NAN
+            } else {
+                n.powf(*m)
+            }),
+            (Object::Number(n), Object::Bool(b)) => Object::Number(match (*n, *b) {
+                (0.0, false) => f64::NAN,
+                (_, false) => 1.0,
+                (n, true) => n,
+            }),
+            (Object::Bool(b), Object::Number(n)) => Object::Number(match (*b, *n) {
+                (false, 0.0) => f64::NAN,
+                (false, _) => 0.0,
+                (true, _) => 1.0,
+            }),
+            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
+                (true, true) => 1.0,
+                (true, false) => 1.0,
+                (false, true) => 0.0,
+                (false, false) => f64::NAN,
+            }),
+            (a, b) => {
+                return Err(RuntimeError::type_mismatch(
+                    "**",
+                    &[a.r#typeof(), b.r#typeof()],
+                ))
+            }
+        })
+    }
+}
+
+impl<'a> Rem<&'a Object> for &'a Object {
+    type Output = Result<Object, RuntimeError>;
+
+    fn rem(self, rhs: &'a Object) -> Self::Output {
+        Ok(match (self, rhs) {
+            (Object::Int(_) | Object::BigInt(_), Object::Int(0)) => {
+                return Err(RuntimeError::new(RuntimeErrorKind::DivisionByZero))
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::Int(_) | Object::BigInt(_)) => {
+                int_op(self, rhs, i64::checked_rem, |a, b| a.div_rem(b).1)
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::Number(_)) => {
+                Object::Number(self.num()?).rem(rhs)?
+            }
+            (Object::Number(_), Object::Int(_) | Object::BigInt(_)) => {
+                self.rem(&Object::Number(rhs.num()?))?
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::Bool(b)) => {
+                self.rem(&Object::Int(*b as i64))?
+            }
+            (Object::Bool(a), Object::Int(_) | Object::BigInt(_)) => {
+                Object::Int(*a as i64).rem(rhs)?
+            }
+            (Object::Number(a), Object::Number(b)) => Object::Number(a % b),
+            (Object::Number(_), Object::Bool(b)) => Object::Number(match b {
+                true => 0.0,
+                false => f64::NAN,
+            }),
+            (Object::Bool(b), Object::Number(_)) => Object::Number(match b {
+                true => 0.0,
+                false => 1.0,
+            }),
+            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
+                (_, true) => 0.0,
+                (_, false) => f64::NAN,
+            }),
+            (a, b) => {
+                return Err(RuntimeError::type_mismatch(
+                    "%",
+                    &[a.r#typeof(), b.r#typeof()],
+                ))
+            }
+        })
+    }
+}
+
+impl Not for &Object {
+    type Output = Result<Object, RuntimeError>;
+
+    /// Negated truthiness, agrees with conditions.
+    fn not(self) -> Self::Output {
+        Ok(Object::Bool(!self.bool()?))
+    }
+}
+
+impl Neg for &Object {
+    type Output = Result<Object, RuntimeError>;
+
+    fn neg(self) -> Self::Output {
+        Ok(match self {
+            Object::Number(n) => Object::Number(-n),
+            Object::Int(n) => n
+                .checked_neg()
+                .map(Object::Int)
+                .unwrap_or_else(|| Object::from(-&BigInt::from(*n))),
+            Object::BigInt(n) => Object::from(-&**n),
+            Object::Bool(b) => Object::Int(-(*b as i64)),
+            Object::Null => return Err(RuntimeError::type_mismatch("-", &[self.r#typeof()])),
+            _ => Object::Number(-self.num()?),
+        })
+    }
+}
+
+impl<'a> BitOr<&'a Object> for &'a Object {
+    type Output = Result<Object, RuntimeError>;
+
+    fn bitor(self, rhs: &'a Object) -> Self::Output {
+        Ok(match (self, rhs) {
+            (Object::Int(_) | Object::BigInt(_), Object::Int(_) | Object::BigInt(_)) => {
+                int_op(self, rhs, |a, b| Some(a | b), |a, b| a | b)
+            }
+            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
+                (true, true) => 1.0,
+                (true, false) => 1.0,
+                (false, true) => 1.0,
+                (false, false) => 0.0,
+            }),
+            (
+                Object::Int(_) | Object::BigInt(_) | Object::Number(_) | Object::Bool(_),
+                Object::Int(_) | Object::BigInt(_) | Object::Number(_) | Object::Bool(_),
+            ) => bitwise_operand(self, "|")?.bitor(&bitwise_operand(rhs, "|")?)?,
+            (Object::Set(a), Object::Set(b)) => Object::set(a.borrow().union(&b.borrow())?),
+            (a, b) => {
+                return Err(RuntimeError::type_mismatch(
+                    "|",
+                    &[a.r#typeof(), b.r#typeof()],
+                ))
+            }
+        })
+    }
+}
+
+impl<'a> BitAnd<&'a Object> for &'a Object {
+    type Output = Result<Object, RuntimeError>;
+
+    fn bitand(self, rhs: &'a Object) -> Self::Output {
+        Ok(match (self, rhs) {
+            (Object::Int(_) | Object::BigInt(_), Object::Int(_) | Object::BigInt(_)) => {
+                int_op(self, rhs, |a, b| Some(a & b), |a, b| a & b)
+            }
+            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
+                (true, true) => 1.0,
+                (true, false) => 0.0,
+                (false, true) => 0.0,
+                (false, false) => 0.0,
+            }),
+            (
+                Object::Int(_) | Object::BigInt(_) | Object::Number(_) | Object::Bool(_),
+                Object::Int(_) | Object::BigInt(_) | Object::Number(_) | Object::Bool(_),
+            ) => bitwise_operand(self, "&")?.bitand(&bitwise_operand(rhs, "&")?)?,
+            (Object::Set(a), Object::Set(b)) => Object::set(a.borrow().intersection(&b.borrow())?),
+            (a, b) => {
+                return Err(RuntimeError::type_mismatch(
+                    "&",
+                    &[a.r#typeof(), b.r#typeof()],
+                ))
+            }
+        })
+    }
+}
+
+impl<'a> BitXor<&'a Object> for &'a Object {
+    type Output = Result<Object, RuntimeError>;
+
+    fn bitxor(self, rhs: &'a Object) -> Self::Output {
+        Ok(match (self, rhs) {
+            (Object::Int(_) | Object::BigInt(_), Object::Int(_) | Object::BigInt(_)) => {
+                int_op(self, rhs, |a, b| Some(a ^ b), |a, b| a ^ b)
+            }
+            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
+                (true, true) => 0.0,
+                (true, false) => 1.0,
+                (false, true) => 1.0,
+                (false, false) => 0.0,
+            }),
+            (
+                Object::Int(_) | Object::BigInt(_) | Object::Number(_) | Object::Bool(_),
+                Object::Int(_) | Object::BigInt(_) | Object::Number(_) | Object::Bool(_),
+            ) => bitwise_operand(self, "^")?.bitxor(&bitwise_operand(rhs, "^")?)?,
+            (Object::Set(a), Object::Set(b)) => {
+                Object::set(a.borrow().symmetric_difference(&b.borrow())?)
+            }
+            (a, b) => {
+                return Err(RuntimeError::type_mismatch(
+                    "^",
+                    &[a.r#typeof(), b.r#typeof()],
+                ))
+            }
+        })
+    }
+}
+
+impl<'a> Shr<&'a Object> for &'a Object {
+    type Output = Result<Object, RuntimeError>;
+
+    fn shr(self, rhs: &'a Object) -> Self::Output {
+        Ok(match (self, rhs) {
+            (Object::Int(_) | Object::BigInt(_), Object::Int(b)) if *b < 0 => {
+                return Err(negative_shift(">>"))
+            }
+            (Object::Int(a), Object::Int(b)) => Object::Int(a >> (*b).min(63)),
+            (Object::BigInt(a), Object::Int(b)) => {
+                Object::from(a.shr(u32::try_from(*b).unwrap_or(u32::MAX)))
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::BigInt(b)) => {
+                return Err(shift_out_of_range(">>", b))
+            }
+            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
+                (true, true) => 0.0,
+                (true, false) => 1.0,
+                (false, true) => 0.0,
+                (false, false) => 0.0,
+            }),
+            (
+                Object::Int(_) | Object::BigInt(_) | Object::Number(_) | Object::Bool(_),
+                Object::Int(_) | Object::Number(_) | Object::Bool(_),
+            ) => bitwise_operand(self, ">>")?.shr(&bitwise_operand(rhs, ">>")?)?,
+            (a, b) => {
+                return Err(RuntimeError::type_mismatch(
+                    ">>",
+                    &[a.r#typeof(), b.r#typeof()],
+                ))
+            }
+        })
+    }
+}
+
+impl<'a> Shl<&'a Object> for &'a Object {
+    type Output = Result<Object, RuntimeError>;
+
+    fn shl(self, rhs: &'a Object) -> Self::Output {
+        Ok(match (self, rhs) {
+            (Object::Int(_) | Object::BigInt(_), Object::Int(b)) if *b < 0 => {
+                return Err(negative_shift("<<"))
+            }
+            (Object::Int(0), Object::Int(_)) => Object::Int(0),
+            (Object::Int(_) | Object::BigInt(_), Object::Int(b)) => match u32::try_from(*b) {
+                Ok(bits) => match self {
+                    Object::Int(a) => checked_shl(*a, *b).map(Object::Int),
+                    _ => None,
+                }
+                .unwrap_or_else(|| Object::from(to_bigint(self).shl(bits))),
+                Err(_) => return Err(overflow("<<")),
+            },
+            (Object::Int(_) | Object::BigInt(_), Object::BigInt(b)) => {
+                return Err(shift_out_of_range("<<", b))
+            }
+            (Object::Bool(b1), Object::Bool(b2)) => Object::Number(match (b1, b2) {
+                (true, true) => 2.0,
+                (true, false) => 1.0,
+                (false, true) => 0.0,
+                (false, false) => 0.0,
+            }),
+            (
+                Object::Int(_) | Object::BigInt(_) | Object::Number(_) | Object::Bool(_),
+                Object::Int(_) | Object::Number(_) | Object::Bool(_),
+            ) => bitwise_operand(self, "<<")?.shl(&bitwise_operand(rhs, "<<")?)?,
+            (a, b) => {
+                return Err(RuntimeError::type_mismatch(
+                    "<<",
+                    &[a.r#typeof(), b.r#typeof()],
+                ))
+            }
+        })
+    }
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_nested(other, &mut vec![])
+    }
+}
+
+impl Object {
+    /// `==` for values nested in the pairs of containers in `open`, which are
+    /// being compared already.
+    fn eq_nested(&self, other: &Object, open: &mut Vec<(*const (), *const ())>) -> bool {
+        match (self, other) {
+            (Object::Number(a), Object::Number(b)) => (*a).eq(b),
+            (Object::Int(a), Object::Int(b)) => a.eq(b),
+            (Object::BigInt(a), Object::BigInt(b)) => a.eq(b),
+            (Object::BigInt(a), Object::Number(b)) | (Object::Number(b), Object::BigInt(a)) => {
+                BigInt::from_f64(*b).as_ref() == Some(a)
+            }
+            (Object::Int(a), Object::Number(b)) | (Object::Number(b), Object::Int(a)) => {
+                cmp_int_float(*a, *b) == Some(Ordering::Equal)
+            }
+            (Object::Bool(b1), Object::Bool(b2)) => b1.eq(b2),
+            (Object::String(s1), Object::String(s2)) => s1.borrow().eq(&*s2.borrow()),
+            (Object::Char(c1), Object::Char(c2)) => c1.eq(c2),
+            (Object::Null, Object::Null) => true,
+            (Object::Range(r1), Object::Range(r2)) => r1.eq(r2),
+            (Object::Function(f1), Object::Function(f2)) => Rc::ptr_eq(f1, f2),
+            (Object::List(l1), Object::List(l2)) => eq_containers(l1, l2, open, |open| {
+                items_eq(&l1.borrow(), &l2.borrow(), open)
+            }),
+            (Object::Map(m1), Object::Map(m2)) => eq_containers(m1, m2, open, |open| {
+                m1.borrow().eq_by(&m2.borrow(), |a, b| a.eq_nested(b, open))
+            }),
+            (Object::Tuple(t1), Object::Tuple(t2)) => items_eq(t1, t2, open),
+            (Object::Set(s1), Object::Set(s2)) => {
+                eq_containers(s1, s2, open, |_| s1.borrow().eq(&*s2.borrow()))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Compares two containers with `eq` unless they are the same one or already
+/// being compared. Meeting a pair again means both sides contain themselves at
+/// the same place, so the comparison in progress decides.
+fn eq_containers<T>(
+    a: &Rc<T>,
+    b: &Rc<T>,
+    open: &mut Vec<(*const (), *const ())>,
+    eq: impl FnOnce(&mut Vec<(*const (), *const ())>) -> bool,
+) -> bool {
+    let pair = (Rc::as_ptr(a) as *const (), Rc::as_ptr(b) as *const ());
+    if Rc::ptr_eq(a, b) || open.contains(&pair) {
+        return true;
+    }
+    open.push(pair);
+    let equal = eq(open);
+    open.pop();
+    equal
+}
+
+fn items_eq(a: &[Object], b: &[Object], open: &mut Vec<(*const (), *const ())>) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.eq_nested(b, open))
+}
+
+impl PartialOrd for Object {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Object::Number(a), Object::Number(b)) => a.partial_cmp(b),
+            (Object::Int(a), Object::Int(b)) => a.partial_cmp(b),
+            (Object::Int(_) | Object::BigInt(_), Object::Int(_) | Object::BigInt(_)) => {
+                to_bigint(self).partial_cmp(&to_bigint(other))
+            }
+            (Object::BigInt(a), Object::Number(b)) => a.to_f64().partial_cmp(b),
+            (Object::Number(a), Object::BigInt(b)) => a.partial_cmp(&b.to_f64()),
+            (Object::Int(a), Object::Number(b)) => cmp_int_float(*a, *b),
+            (Object::Number(a), Object::Int(b)) => cmp_int_float(*b, *a).map(Ordering::reverse),
+            (Object::Bool(b), Object::Int(_) | Object::BigInt(_)) => {
+                Object::Int(*b as i64).partial_cmp(other)
+            }
+            (Object::Int(_) | Object::BigInt(_), Object::Bool(_)) => {
+                other.partial_cmp(self).map(Ordering::reverse)
+            }
+            (Object::Bool(b), Object::Number(n)) => match (*b, n) {
+                (true, n) => 1.0.partial_cmp(n),
+                (false, n) => 0.0.partial_cmp(n),
+            },
+            (Object::Number(_), Object::Bool(_)) => other.partial_cmp(self),
+            (Object::Bool(b1), Object::Bool(b2)) => b1.partial_cmp(b2),
+            (Object::String(s1), Object::String(s2)) => s1.borrow().partial_cmp(&*s2.borrow()),
+            (Object::Char(c1), Object::Char(c2)) => c1.partial_cmp(c2),
+            // lexicographic, the first pair of items that differ decides
+            (Object::Tuple(t1), Object::Tuple(t2)) => t1.partial_cmp(t2),
+            _ => None,
+        }
+    }
+}
+
+// #[allow(unused)]
+impl Object {
+    pub fn eq(&self, other: &Object) -> Object {
+        Object::Bool(self == other)
+    }
+    pub fn ne(&self, other: &Object) -> Object {
+        Object::Bool(self != other)
+    }
+
+    pub fn gt(&self, other: &Object) -> Result<Object, RuntimeError> {
+        self.check_ordered(other, ">")?;
+        Ok(Object::Bool(self > other))
+    }
+    pub fn lt(&self, other: &Object) -> Result<Object, RuntimeError> {
+        self.check_ordered(other, "<")?;
+        Ok(Object::Bool(self < other))
+    }
+    pub fn gte(&self, other: &Object) -> Result<Object, RuntimeError> {
+        self.check_ordered(other, ">=")?;
+        Ok(Object::Bool(self >= other))
+    }
+    pub fn lte(&self, other: &Object) -> Result<Object, RuntimeError> {
+        self.check_ordered(other, "<=")?;
+        Ok(Object::Bool(self <= other))
+    }
+
+    /// `Null` has no order, comparing it would silently give `false`.
+    fn check_ordered(&self, other: &Object, op: &str) -> Result<(), RuntimeError> {
+        match (self, other) {
+            (Object::Null, _) | (_, Object::Null) => Err(RuntimeError::type_mismatch(
+                op,
+                &[self.r#typeof(), other.r#typeof()],
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn list(items: Vec<Object>) -> Object {
+        Object::List(Rc::new(RefCell::new(items)))
+    }
+
+    pub fn map(map: Map) -> Object {
+        Object::Map(Rc::new(RefCell::new(map)))
+    }
+
+    pub fn tuple(items: Vec<Object>) -> Object {
+        Object::Tuple(Rc::new(items))
+    }
+
+    pub fn set(set: Set) -> Object {
+        Object::Set(Rc::new(RefCell::new(set)))
+    }
+
+    /// `self[index]`. Lists, tuples and strings can be indexed by an `Int`, negative
+    /// indices count from the end, or sliced by a `Range` whose negative bounds
+    /// count from the end as well. Maps are indexed by key.
+    pub fn idx(&self, index: &Object) -> Result<Object, RuntimeError> {
+        match (self, index) {
+            (Object::Map(map), key) => match map.borrow().get(key)? {
+                Some(value) => Ok(value.clone()),
+                None => Err(RuntimeError::new(RuntimeErrorKind::KeyNotFound(key.repr()))),
+            },
+            (Object::List(list), Object::Int(i)) => {
+                let list = list.borrow();
+                Ok(list[normalize_index(*i, list.len())?].clone())
+            }
+            (Object::List(list), Object::Range(range)) => {
+                Ok(Object::list(slice_items(&list.borrow(), range)?))
+            }
+            (Object::Tuple(items), Object::Int(i)) => {
+                Ok(items[normalize_index(*i, items.len())?].clone())
+            }
+            (Object::Tuple(items), Object::Range(range)) => {
+                Ok(Object::tuple(slice_items(items, range)?))
+            }
+            (Object::String(s), Object::Int(i)) => {
+                let chars: Vec<char> = s.borrow().chars().collect();
+                Ok(Object::Char(chars[normalize_index(*i, chars.len())?]))
+            }
+            (Object::String(s), Object::Range(range)) => {
+                let chars: Vec<char> = s.borrow().chars().collect();
+                let sliced = slice_range(range, chars.len())?
+                    .iter()
+                    .map(|i| Ok(chars[normalize_index(i, chars.len())?]))
+                    .collect::<Result<String, RuntimeError>>()?;
+                Ok(Object::String(Rc::new(RefCell::new(sliced))))
+            }
+            (Object::List(_) | Object::Tuple(_) | Object::String(_), _) => Err(
+                RuntimeError::type_mismatch("[]", &[self.r#typeof(), index.r#typeof()]),
+            ),
+            _ => Err(RuntimeError::new(RuntimeErrorKind::NotIndexable(
+                self.r#typeof(),
+            ))),
+        }
+    }
+
+    /// `self[index] = value`, only lists and maps support it. Assigning to a
+    /// missing map key adds it.
+    pub fn set_idx(&self, index: &Object, value: Object) -> Result<(), RuntimeError> {
+        match (self, index) {
+            (Object::Map(map), key) => map.borrow_mut().insert(key.clone(), value),
+            (Object::List(list), Object::Int(i)) => {
+                let mut list = list.borrow_mut();
+                let i = normalize_index(*i, list.len())?;
+                list[i] = value;
+                Ok(())
+            }
+            (Object::List(_), _) => Err(RuntimeError::type_mismatch(
+                "[]=",
+                &[self.r#typeof(), index.r#typeof()],
+            )),
+            (Object::String(_) | Object::Tuple(_), _) => Err(RuntimeError::new(
+                RuntimeErrorKind::ItemAssignment(self.r#typeof()),
+            )),
+            _ => Err(RuntimeError::new(RuntimeErrorKind::NotIndexable(
+                self.r#typeof(),
+            ))),
+        }
+    }
+
+    /// Truthiness of the value, as used by conditions. Zero, `NaN`, `false`,
+    /// `'\0'`, empty strings, ranges and collections are falsy, everything
+    /// else is truthy. `Null` has no truthiness.
+    pub fn bool(&self) -> Result<bool, RuntimeError> {
+        Ok(match self {
+            Object::Number(n) => *n != 0.0 && !n.is_nan(),
+            Object::Int(n) => *n != 0,
+            Object::BigInt(_) => true,
+            Object::Bool(b) => *b,
+            Object::String(s) => !s.borrow().is_empty(),
+            Object::Char(c) => *c != '\0',
+            Object::Null => return Err(RuntimeError::new(RuntimeErrorKind::NullCondition)),
+            Object::Iterator(_) => true,
+            Object::Range(range) => range.first().is_some(),
+            Object::Function(_) => true,
+            Object::List(list) => !list.borrow().is_empty(),
+            Object::Map(map) => map.borrow().len() != 0,
+            Object::Tuple(items) => !items.is_empty(),
+            Object::Set(set) => set.borrow().len() != 0,
+        })
+    }
+
+    /// Builds `start..end` or `start..=end`, both bounds must be `Int`.
+    pub fn range(start: &Object, end: &Object, inclusive: bool) -> Result<Object, RuntimeError> {
+        match (start, end) {
+            (Object::Int(start), Object::Int(end)) => {
+                Ok(Object::Range(Range::new(*start, *end, inclusive)))
+            }
+            _ => Err(RuntimeError::type_mismatch(
+                if inclusive { "..=" } else { ".." },
+                &[start.r#typeof(), end.r#typeof()],
+            )),
+        }
+    }
+
+    /// Membership test behind `item in self`.
+    pub fn contains(&self, item: &Object) -> Result<bool, RuntimeError> {
+        Ok(match (self, item) {
+            (Object::Range(range), Object::Int(n)) => range.contains(*n),
+            (Object::Range(range), Object::Number(n)) => {
+                n.fract() == 0.0 && *n == (*n as i64) as f64 && range.contains(*n as i64)
+            }
+            (Object::Range(_), Object::BigInt(_)) => false,
+            (Object::String(s), Object::Char(c)) => s.borrow().contains(*c),
+            (Object::String(s), Object::String(sub)) => s.borrow().contains(&*sub.borrow()),
+            (Object::List(list), _) => list.borrow().contains(item),
+            (Object::Map(map), key) => map.borrow().contains(key)?,
+            (Object::Tuple(items), _) => items.contains(item),
+            (Object::Set(set), _) => set.borrow().contains(item)?,
+            _ => {
+                return Err(RuntimeError::type_mismatch(
+                    "in",
+                    &[item.r#typeof(), self.r#typeof()],
+                ))
+            }
+        })
+    }
+
+    /// Calls the built-in method `name` of the object.
+    pub fn call_method(&self, name: &str, args: &[Object]) -> Result<Object, RuntimeError> {
+        let arity = match (self, name) {
+            (Object::Range(_), "step")
+            | (Object::List(_), "push")
+            | (Object::Map(_), "get")
+            | (Object::Map(_) | Object::Set(_), "remove")
+            | (Object::Set(_), "add") => 1,
+            (Object::Range(_), "rev")
+            | (
+                Object::List(_)
+                | Object::Tuple(_)
+                | Object::String(_)
+                | Object::Map(_)
+                | Object::Set(_),
+                "len",
+            )
+            | (Object::Map(_), "keys" | "values" | "items")
+            | (Object::Iterator(_), "next")
+            | (_, "iter") => 0,
+            _ => {
+                return Err(RuntimeError::new(RuntimeErrorKind::NoMethod {
+                    r#type: self.r#typeof(),
+                    name: name.to_string(),
+                }))
+            }
+        };
+        if args.len() != arity {
+            return Err(RuntimeError::new(RuntimeErrorKind::WrongArity {
+                name: name.to_string(),
+                expected: arity,
+                found: args.len(),
+            }));
+        }
+        match (self, name, args) {
+            (Object::Range(range), "step", [Object::Int(step)]) => {
+                Ok(Object::Range(range.step(*step)?))
+            }
+            (Object::Range(_), "step", [step]) => {
+                Err(RuntimeError::new(RuntimeErrorKind::InvalidValue(format!(
+                    "Range step must be of type Int, not {}",
+                    step.r#typeof()
+                ))))
+            }
+            (Object::Range(range), "rev", _) => Ok(Object::Range(range.rev())),
+            (Object::List(list), "len", _) => Ok(Object::Int(list.borrow().len() as i64)),
+            (Object::String(s), "len", _) => Ok(Object::Int(s.borrow().chars().count() as i64)),
+            (Object::List(list), "push", [item]) => {
+                list.borrow_mut().push(item.clone());
+                Ok(Object::Null)
+            }
+            (Object::Map(map), "get", [key]) => {
+                Ok(map.borrow().get(key)?.cloned().unwrap_or(Object::Null))
+            }
+            (Object::Map(map), "len", _) => Ok(Object::Int(map.borrow().len() as i64)),
+            (Object::Map(map), "keys", _) => Ok(Object::list(
+                map.borrow().iter().map(|(key, _)| key.clone()).collect(),
+            )),
+            (Object::Map(map), "values", _) => Ok(Object::list(
+                map.borrow()
+                    .iter()
+                    .map(|(_, value)| value.clone())
+                    .collect(),
+            )),
+            (Object::Map(map), "items", _) => Ok(Object::list(
+                map.borrow()
+                    .iter()
+                    .map(|(key, value)| Object::tuple(vec![key.clone(), value.clone()]))
+                    .collect(),
+            )),
+            (Object::Tuple(items), "len", _) => Ok(Object::Int(items.len() as i64)),
+            (Object::Set(set), "len", _) => Ok(Object::Int(set.borrow().len() as i64)),
+            (Object::Set(set), "add", [item]) => {
+                Ok(Object::Bool(set.borrow_mut().insert(item.clone())?))
+            }
+            (Object::Set(set), "remove", [item]) => {
+                Ok(Object::Bool(set.borrow_mut().remove(item)?))
+            }
+            (Object::Map(map), "remove", [key]) => match map.borrow_mut().remove(key)? {
+                Some(value) => Ok(value),
+                None => Err(RuntimeError::new(RuntimeErrorKind::KeyNotFound(key.repr()))),
+            },
+            (Object::Iterator(iter), "next", _) => Ok(iter.next()?.unwrap_or(Object::Null)),
+            (Object::Function(function), "iter", _) => Ok(Object::Iterator(Iter::new(
+                FunctionIter::new(function.clone()),
+            ))),
+            (_, "iter", _) => Ok(Object::Iterator(self.iter()?)),
+            _ => unreachable!("arity is only known for existing methods"),
+        }
+    }
+
+    /// Starts iterating over the value. Strings are iterated by `Char`, ranges
+    /// lazily by `Int`, collections over a snapshot of their items (the keys of
+    /// a map), an iterator yields itself.
+    pub fn iter(&self) -> Result<Iter, RuntimeError> {
+        match self {
+            Object::Iterator(iter) => Ok(iter.clone()),
+            Object::Range(range) => Ok(Iter::new(range.iter().map(Object::Int))),
+            Object::List(list) => Ok(Iter::new(list.borrow().clone().into_iter())),
+            Object::Map(map) => {
+                let keys: Vec<Object> = map.borrow().iter().map(|(key, _)| key.clone()).collect();
+                Ok(Iter::new(keys.into_iter()))
+            }
+            Object::Tuple(items) => Ok(Iter::new((**items).clone().into_iter())),
+            Object::Set(set) => {
+                let items: Vec<Object> = set.borrow().iter().cloned().collect();
+                Ok(Iter::new(items.into_iter()))
+            }
+            Object::String(s) => {
+                let chars: Vec<char> = s.borrow().chars().collect();
+                Ok(Iter::new(chars.into_iter().map(Object::Char)))
+            }
+            _ => Err(RuntimeError::new(RuntimeErrorKind::NotIterable(
+                self.r#typeof(),
+            ))),
+        }
+    }
+
+    pub fn num(&self) -> Result<f64, RuntimeError> {
+        Ok(match self {
+            Object::Number(n) => *n,
+            Object::Int(n) => *n as f64,
+            Object::BigInt(n) => n.to_f64(),
+            Object::Bool(b) => {
+                if *b {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Object::String(s) => s.borrow().parse::<f64>().unwrap_or(f64::NAN),
+            Object::Char(c) => *c as i64 as f64,
+            Object::Null
+            | Object::Iterator(_)
+            | Object::Range(_)
+            | Object::Function(_)
+            | Object::List(_)
+            | Object::Map(_)
+            | Object::Tuple(_)
+            | Object::Set(_) => {
+                return Err(RuntimeError::new(RuntimeErrorKind::InvalidValue(format!(
+                    "{} can not be converted to Number",
+                    self.r#typeof()
+                ))))
+            }
+        })
+    }
+
+    pub fn str(&self) -> String {
+        self.repr()
+    }
+
+    pub fn repr(&self) -> String {
+        self.repr_nested(&mut vec![])
+    }
+
+    /// `repr` of a value nested in the containers in `open`. A container that
+    /// contains itself is shown as `[...]`, `{...}` or `{,...}` the second time.
+    fn repr_nested(&self, open: &mut Vec<*const ()>) -> String {
+        match self {
+            Object::Number(n) => n.to_string(),
+            Object::Int(n) => n.to_string(),
+            Object::BigInt(n) => n.to_string(),
+            Object::Bool(b) => b.to_string(),
+            Object::Char(c) => format!("{:?}", c),
+            Object::String(s) => format!("{:?}", s.borrow()),
+            Object::Null => "null".to_string(),
+            Object::Iterator(_) => "<iterator>".to_string(),
+            Object::Range(range) => range.to_string(),
+            Object::List(list) => repr_container(list, "[...]", open, |open| {
+                format!("[{}]", join_repr(list.borrow().iter(), open))
+            }),
+            Object::Map(map) => repr_container(map, "{...}", open, |open| {
+                let entries = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| {
+                        format!("{}: {}", key.repr_nested(open), value.repr_nested(open))
+                    })
+                    .collect::<Vec<_>>();
+                format!("{{{}}}", entries.join(", "))
+            }),
+            Object::Tuple(items) => match items.as_slice() {
+                [item] => format!("({},)", item.repr_nested(open)),
+                items => format!("({})", join_repr(items.iter(), open)),
+            },
+            Object::Set(set) => {
+                repr_container(set, "{,...}", open, |open| match set.borrow().len() {
+                    0 => "{,}".to_string(),
+                    1 => format!("{{{},}}", join_repr(set.borrow().iter(), open)),
+                    _ => format!("{{{}}}", join_repr(set.borrow().iter(), open)),
+                })
+            }
+            Object::Function(function) => match function.name {
+                Some(_) => format!("<fn {}>", function),
+                None => function.to_string(),
+            },
+        }
+    }
+}
+
+impl TypeOf for &Object {
+    fn r#typeof(self) -> String {
+        match self {
+            Object::Number(_) => "Number".to_string(),
+            Object::Int(_) | Object::BigInt(_) => "Int".to_string(),
+            Object::Bool(_) => "Bool".to_string(),
+            Object::String(_) => "String".to_string(),
+            Object::Char(_) => "Char".to_string(),
+            Object::Null => "Null".to_string(),
+            Object::Iterator(_) => "Iterator".to_string(),
+            Object::Range(_) => "Range".to_string(),
+            Object::Function(_) => "Function".to_string(),
+            Object::List(_) => "List".to_string(),
+            Object::Map(_) => "Map".to_string(),
+            Object::Tuple(_) => "Tuple".to_string(),
+            Object::Set(_) => "Set".to_string(),
+        }
+    }
+}
+
+impl Display for Object {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.repr())
+    }
+}
+
+/// Turns a possibly negative index into a position in a sequence of length
+/// `len`.
+fn normalize_index(index: i64, len: usize) -> Result<usize, RuntimeError> {
+    let position = if index < 0 {
+        index.checked_add(len as i64)
+    } else {
+        Some(index)
+    };
+    match position {
+        Some(position) if position >= 0 && (position as usize) < len => Ok(position as usize),
+        _ => Err(RuntimeError::new(RuntimeErrorKind::IndexOutOfRange {
+            index,
+            len,
+        })),
+    }
+}
+
+/// Resolves negative bounds of a slicing range against the length. A bound
+/// outside of `0..=len` once resolved is reported as written.
+fn slice_range(range: &Range, len: usize) -> Result<Range, RuntimeError> {
+    let resolve = |bound: i64| {
+        let position = if bound < 0 {
+            bound.checked_add(len as i64)
+        } else {
+            Some(bound)
+        };
+        match position {
+            Some(position) if position >= 0 && position as usize <= len => Ok(position),
+            _ => Err(RuntimeError::new(RuntimeErrorKind::IndexOutOfRange {
+                index: bound,
+                len,
+            })),
+        }
+    };
+    Ok(Range {
+        start: resolve(range.start)?,
+        end: resolve(range.end)?,
+        ..*range
+    })
+}
+
+/// The items of `items` picked by `range`, see `slice_range`.
+fn slice_items(items: &[Object], range: &Range) -> Result<Vec<Object>, RuntimeError> {
+    slice_range(range, items.len())?
+        .iter()
+        .map(|i| Ok(items[normalize_index(i, items.len())?].clone()))
+        .collect()
+}
+
+fn join_repr<'a>(items: impl Iterator<Item = &'a Object>, open: &mut Vec<*const ()>) -> String {
+    items
+        .map(|item| item.repr_nested(open))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Shows a container with `repr`, or as `cycle` if it's in `open` already.
+fn repr_container<T>(
+    container: &Rc<T>,
+    cycle: &str,
+    open: &mut Vec<*const ()>,
+    repr: impl FnOnce(&mut Vec<*const ()>) -> String,
+) -> String {
+    let pointer = Rc::as_ptr(container) as *const ();
+    if open.contains(&pointer) {
+        return cycle.to_string();
+    }
+    open.push(pointer);
+    let shown = repr(open);
+    open.pop();
+    shown
+}
+
+fn overflow(op: &str) -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::IntegerOverflow(op.to_string()))
+}
+
+fn negative_shift(op: &str) -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::InvalidValue(format!(
+        "Operator '{}' can't shift by a negative amount",
+        op
+    )))
+}
+
+/// Shift by an amount too large to be an `Int`.
+fn shift_out_of_range(op: &str, amount: &BigInt) -> RuntimeError {
+    if amount.is_negative() {
+        return negative_shift(op);
+    }
+    RuntimeError::new(RuntimeErrorKind::InvalidValue(format!(
+        "Operator '{}' can't shift by {} bits, the amount is out of range",
+        op, amount
+    )))
+}
+
+/// Operand of a bitwise operator as an `Int`. A `Number` must be a whole
+/// number that fits into `Int`, it's never truncated or saturated.
+fn bitwise_operand(n: &Object, op: &str) -> Result<Object, RuntimeError> {
+    Ok(match n {
+        // `i64::MAX as f64` rounds up to 2^63, which doesn't fit
+        Object::Number(f) if f.fract() == 0.0 && *f >= i64::MIN as f64 && *f < i64::MAX as f64 => {
+            Object::Int(*f as i64)
+        }
+        Object::Number(_) => {
+            return Err(RuntimeError::new(RuntimeErrorKind::InvalidValue(format!(
+                "Operator '{}' needs whole numbers that fit into an Int, found {}",
+                op,
+                n.repr()
+            ))))
+        }
+        Object::Bool(b) => Object::Int(*b as i64),
+        n => n.clone(),
+    })
+}
+
+/// Arithmetic on the code points of `Char` operands, the other operand must
+/// be a whole number. The result must be a valid `Char`.
+fn char_op(
+    lhs: &Object,
+    rhs: &Object,
+    op: &str,
+    f: fn(i64, i64) -> Option<i64>,
+) -> Result<Object, RuntimeError> {
+    let code_point = |n: &Object| match n {
+        Object::Char(c) => Ok(*c as i64),
+        Object::Int(n) => Ok(*n),
+        // `i64::MAX as f64` rounds up to 2^63, which doesn't fit
+        Object::Number(f) if f.fract() == 0.0 && *f >= i64::MIN as f64 && *f < i64::MAX as f64 => {
+            Ok(*f as i64)
+        }
+        n => Err(RuntimeError::new(RuntimeErrorKind::InvalidValue(format!(
+            "Operator '{}' needs a whole number to offset a Char, found {}",
+            op,
+            n.repr()
+        )))),
+    };
+    f(code_point(lhs)?, code_point(rhs)?)
+        .and_then(|n| u32::try_from(n).ok())
+        .and_then(char::from_u32)
+        .map(Object::Char)
+        .ok_or_else(|| {
+            RuntimeError::new(RuntimeErrorKind::InvalidValue(format!(
+                "{} {} {} is not a valid Char",
+                lhs.repr(),
+                op,
+                rhs.repr()
+            )))
+        })
+}
+
+/// Integer arithmetic that is done on `i64` when possible and on `BigInt` when
+/// the `i64` operation overflows. Both operands must be `Int` or `BigInt`.
+fn int_op(
+    lhs: &Object,
+    rhs: &Object,
+    small: fn(i64, i64) -> Option<i64>,
+    big: fn(&BigInt, &BigInt) -> BigInt,
+) -> Object {
+    if let (Object::Int(a), Object::Int(b)) = (lhs, rhs) {
+        if let Some(n) = small(*a, *b) {
+            return Object::Int(n);
+        }
+    }
+    Object::from(big(&to_bigint(lhs), &to_bigint(rhs)))
+}
+
+fn to_bigint(n: &Object) -> BigInt {
+    match n {
+        Object::Int(n) => BigInt::from(*n),
+        Object::BigInt(n) => (**n).clone(),
+        _ => unreachable!("{} is not an integer", n.r#typeof()),
+    }
+}
+
+/// `a << b` that fails instead of dropping bits.
+fn checked_shl(a: i64, b: i64) -> Option<i64> {
+    let shifted = a.checked_shl(u32::try_from(b).ok()?)?;
+    if shifted >> b == a {
+        Some(shifted)
+    } else {
+        None
+    }
+}
+
+/// Exact comparison of an `Int` with a `Number`, without rounding `i` to `f64`.
+fn cmp_int_float(i: i64, f: f64) -> Option<Ordering> {
+    if f.is_nan() {
+        None
+    } else if f >= i64::MAX as f64 {
+        Some(Ordering::Less)
+    } else if f < i64::MIN as f64 {
+        Some(Ordering::Greater)
+    } else {
+        let trunc = f.trunc();
+        match i.cmp(&(trunc as i64)) {
+            Ordering::Equal => 0.0.partial_cmp(&(f - trunc)),
+            ordering => Some(ordering),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt::Display;
+
+use crate::error::{RuntimeError, RuntimeErrorKind};
+
+/// Integer range `start..end` or `start..=end` walked in steps of `step`. A
+/// negative step walks from `start` down towards `end`. Ranges are never
+/// materialized, iterating produces the values one by one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub start: i64,
+    pub end: i64,
+    pub inclusive: bool,
+    pub step: i64,
+}
+
+impl Range {
+    pub fn new(start: i64, end: i64, inclusive: bool) -> Self {
+        Self {
+            start,
+            end,
+            inclusive,
+            step: 1,
+        }
+    }
+
+    pub fn step(self, step: i64) -> Result<Self, RuntimeError> {
+        if step == 0 {
+            return Err(RuntimeError::new(RuntimeErrorKind::InvalidValue(
+                "Range step can't be zero".to_string(),
+            )));
+        }
+        if step == i64::MIN {
+            return Err(RuntimeError::new(RuntimeErrorKind::IntegerOverflow(
+                "step".to_string(),
+            )));
+        }
+        Ok(Self { step, ..self })
+    }
+
+    /// The same values in the opposite order.
+    pub fn rev(self) -> Self {
+        match self.last() {
+            Some(last) => Self {
+                start: last,
+                end: self.start,
+                inclusive: true,
+                step: -self.step,
+            },
+            None => Self {
+                end: self.start,
+                inclusive: false,
+                ..self
+            },
+        }
+    }
+
+    pub fn first(&self) -> Option<i64> {
+        if self.contains_bound(self.start) {
+            Some(self.start)
+        } else {
+            None
+        }
+    }
+
+    pub fn last(&self) -> Option<i64> {
+        let first = self.first()? as i128;
+        let (step, end) = (self.step as i128, self.end as i128);
+        let end = match (self.inclusive, step > 0) {
+            (true, _) => end,
+            (false, true) => end - 1,
+            (false, false) => end + 1,
+        };
+        Some((first + (end - first) / step * step) as i64)
+    }
+
+    pub fn contains(&self, n: i64) -> bool {
+        self.contains_bound(n) && (n as i128 - self.start as i128) % self.step as i128 == 0
+    }
+
+    /// Whether `n` lies between the start and the end of the range, ignoring
+    /// the step.
+    fn contains_bound(&self, n: i64) -> bool {
+        let (low, high) = if self.step > 0 {
+            (self.start, self.end)
+        } else {
+            (self.end, self.start)
+        };
+        let past_end = if self.step > 0 { n >= high } else { n <= low };
+        let before_start = if self.step > 0 { n < low } else { n > high };
+        !before_start && (!past_end || (self.inclusive && n == self.end))
+    }
+
+    pub fn iter(&self) -> RangeIter {
+        RangeIter {
+            range: *self,
+            next: self.first(),
+        }
+    }
+}
+
+impl Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = if self.inclusive { "..=" } else { ".." };
+        match self.step {
+            1 => write!(f, "{}{}{}", self.start, op, self.end),
+            step => write!(f, "({}{}{}).step({})", self.start, op, self.end, step),
+        }
+    }
+}
+
+pub struct RangeIter {
+    range: Range,
+    next: Option<i64>,
+}
+
+impl Iterator for RangeIter {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        let current = self.next?;
+        self.next = current
+            .checked_add(self.range.step)
+            .filter(|&n| self.range.contains_bound(n));
+        Some(current)
+    }
+}
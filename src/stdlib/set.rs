@@ -0,0 +1,75 @@
+use super::{map::Map, object::Object};
+use crate::error::RuntimeError;
+
+/// Set that remembers the order in which items were first inserted. It's a
+/// `Map` whose values are all `Null`, so the same objects can be items as can
+/// be map keys.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Set(Map);
+
+impl Set {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn contains(&self, item: &Object) -> Result<bool, RuntimeError> {
+        self.0.contains(item)
+    }
+
+    /// Adds `item`, returns whether it wasn't there yet.
+    pub fn insert(&mut self, item: Object) -> Result<bool, RuntimeError> {
+        if self.contains(&item)? {
+            return Ok(false);
+        }
+        self.0.insert(item, Object::Null)?;
+        Ok(true)
+    }
+
+    /// Removes `item`, returns whether it was there.
+    pub fn remove(&mut self, item: &Object) -> Result<bool, RuntimeError> {
+        Ok(self.0.remove(item)?.is_some())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Object> {
+        self.0.iter().map(|(item, _)| item)
+    }
+
+    /// Items of `self`, then the items of `other` that aren't in `self`.
+    pub fn union(&self, other: &Set) -> Result<Set, RuntimeError> {
+        let mut union = self.clone();
+        for item in other.iter() {
+            union.insert(item.clone())?;
+        }
+        Ok(union)
+    }
+
+    pub fn intersection(&self, other: &Set) -> Result<Set, RuntimeError> {
+        self.filter(|item| other.contains(item))
+    }
+
+    pub fn difference(&self, other: &Set) -> Result<Set, RuntimeError> {
+        self.filter(|item| Ok(!other.contains(item)?))
+    }
+
+    /// Items that are in exactly one of the sets.
+    pub fn symmetric_difference(&self, other: &Set) -> Result<Set, RuntimeError> {
+        self.difference(other)?.union(&other.difference(self)?)
+    }
+
+    fn filter(
+        &self,
+        mut keep: impl FnMut(&Object) -> Result<bool, RuntimeError>,
+    ) -> Result<Set, RuntimeError> {
+        let mut set = Set::new();
+        for item in self.iter() {
+            if keep(item)? {
+                set.insert(item.clone())?;
+            }
+        }
+        Ok(set)
+    }
+}